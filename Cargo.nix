@@ -4088,7 +4088,7 @@ rec {
           "user" = [ "feature" ];
           "zerocopy" = [ "fs" "uio" ];
         };
-        resolvedDefaultFeatures = [ "default" "fs" "resource" "uio" ];
+        resolvedDefaultFeatures = [ "default" "fs" "process" "resource" "sched" "signal" "uio" ];
       };
       "nohash-hasher" = rec {
         crateName = "nohash-hasher";
@@ -4624,6 +4624,10 @@ rec {
             name = "ingest";
             packageId = "ingest";
           }
+          {
+            name = "png";
+            packageId = "png";
+          }
           {
             name = "procinfo";
             packageId = "procinfo";
@@ -4797,10 +4801,18 @@ rec {
             name = "either";
             packageId = "either";
           }
+          {
+            name = "libc";
+            packageId = "libc";
+          }
           {
             name = "nix";
             packageId = "nix 0.27.1";
-            features = [ "uio" "resource" "fs" ];
+            features = [ "uio" "resource" "fs" "sched" "signal" ];
+          }
+          {
+            name = "sysinfo";
+            packageId = "sysinfo";
           }
           {
             name = "tracing";
@@ -5457,7 +5469,7 @@ rec {
           {
             name = "nix";
             packageId = "nix 0.27.1";
-            features = [ "uio" "resource" "fs" ];
+            features = [ "uio" "resource" "fs" "sched" "signal" ];
           }
           {
             name = "tracing";
@@ -1,4 +1,4 @@
-use procinfo::{ProcInfo, ProcIngest};
+use procinfo::{ProcInfo, ProcIngest, ProcIngestOptions};
 use std::{
     sync::{
         atomic::{AtomicU8, Ordering},
@@ -49,7 +49,11 @@ impl ProducerStatus {
 impl MetricsConsumer {
     const VIEWING_PROC: u8 = 0;
     const VIEWING_SYS: u8 = 1;
-    pub fn start(ctx: egui::Context, status: &'static Mutex<ProducerStatus>) -> Self {
+    pub fn start(
+        ctx: egui::Context,
+        status: &'static Mutex<ProducerStatus>,
+        proc_options: ProcIngestOptions,
+    ) -> Self {
         let consumer = Self {
             sys_info: Box::leak(Box::new(Mutex::new(SysInfo::default()))),
             proc_info: Box::leak(Box::new(Mutex::new(ProcInfo::new()))),
@@ -57,7 +61,7 @@ impl MetricsConsumer {
         };
         let mut sys_handles = SysHandles::new();
         let producer = MetricsProducer {
-            proc_ingest: ProcIngest::new(),
+            proc_ingest: ProcIngest::new(proc_options),
             sys_old_snapshot: SysSnapshot::new(&mut sys_handles).retire(),
             sys_handles,
             consumer: Self {
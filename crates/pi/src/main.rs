@@ -2,12 +2,13 @@
 compile_error!("pi supports only linux");
 
 use crate::{
-    process::{ProcessNavigation, ProcessTab},
+    process::{ProcessNavigation, ProcessTab, ProcessTabState},
     system::{SystemNavigation, SystemTab},
 };
 use clap::{Parser, Subcommand};
 use eframe::egui::{self, Key, KeyboardShortcut, Modifiers, Ui};
 use ingest::{MetricsConsumer, ProducerStatus};
+use procinfo::ProcIngestOptions;
 use std::{sync::Mutex, thread, time::Duration};
 use tracing_subscriber::Layer;
 
@@ -18,6 +19,13 @@ mod system;
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Read PSS and USS of every process from `smaps_rollup`. Costs noticeably more CPU.
+    #[arg(long)]
+    pss: bool,
+    /// Also sample processes as they fork and exec, to list even those exiting within a second.
+    /// Requires `CAP_NET_ADMIN`.
+    #[arg(long)]
+    proc_events: bool,
     #[command(subcommand)]
     focus: Option<Focus>,
 }
@@ -69,7 +77,7 @@ fn main() {
                         } else {
                             NavigationTab::Process
                         },
-                        process: ProcessNavigation::LoginSessions,
+                        process: ProcessTabState::new(ProcessNavigation::LoginSessions, cli.pss),
                         system: match cli.focus {
                             Some(Focus::Cpu) | None => SystemNavigation::Cpu,
                             Some(Focus::Ram) => SystemNavigation::Ram,
@@ -78,7 +86,14 @@ fn main() {
                             Some(Focus::Gpu) => SystemNavigation::Gpu,
                         },
                     },
-                    metrics: MetricsConsumer::start(cc.egui_ctx.clone(), status),
+                    metrics: MetricsConsumer::start(
+                        cc.egui_ctx.clone(),
+                        status,
+                        ProcIngestOptions {
+                            smaps_rollup: cli.pss,
+                            proc_events: cli.proc_events,
+                        },
+                    ),
                 })
             }
         }),
@@ -109,7 +124,7 @@ struct State {
 }
 struct Navigation {
     tab: NavigationTab,
-    process: ProcessNavigation,
+    process: ProcessTabState,
     system: SystemNavigation,
 }
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -119,7 +134,12 @@ enum NavigationTab {
 }
 impl eframe::App for State {
     fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        let typing = ctx.wants_keyboard_input();
         ctx.input_mut(|i| {
+            if typing {
+                // Keys belong to the focused text field
+                return;
+            }
             if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::SHIFT, Key::P)) {
                 self.nav.tab = NavigationTab::Process;
             } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::SHIFT, Key::S)) {
@@ -158,6 +178,9 @@ pub trait Component {
 }
 
 fn vim_like_scroll(ui: &mut Ui, small_jump: f32, large_jump: f32) {
+    if ui.ctx().wants_keyboard_input() {
+        return;
+    }
     let mut scroll_up = 0.0;
     ui.ctx().input_mut(|i| {
         if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::J)) {
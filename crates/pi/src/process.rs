@@ -1,22 +1,196 @@
-use crate::{show::Show, Component};
+use crate::{
+    show::Show,
+    system::time_series::{TimeSeries, TimeSeriesKind, ValueKind},
+    Component,
+};
 use eframe::egui::{
-    self, style::TextStyle, Color32, Frame, Id, Key, KeyboardShortcut, Modifiers, Sense, Ui,
+    self, style::TextStyle, Align2, Color32, CursorIcon, Frame, Id, Key, KeyboardShortcut,
+    Modifiers, Sense, Ui,
+};
+use procinfo::{
+    CgroupStat, Errno, IoClass, IoPriority, MapRollup, MapUsage, MemoryMap, OpenFile, ProcFilter,
+    ProcInfo, ProcSortBy, ProcStat, ProcTarget, ProcessHistory, ProcessInfo, SchedInfo,
+    SchedPolicy, Signal, TaskInfo, ThreadInfo, UserInfo,
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs::File,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
 };
-use procinfo::{ProcInfo, ProcSortBy, ProcStat};
 
 pub struct ProcessTab;
+pub struct ProcessTabState {
+    nav: ProcessNavigation,
+    columns: BTreeSet<Column>,
+    /// Whether PSS and USS are read, without which their columns would only show 0.
+    pss: bool,
+    /// Nest processes under their parents, and threads under their processes.
+    tree: bool,
+    /// Pids whose subtrees are hidden in the tree.
+    collapsed: HashSet<u32>,
+    /// Pids whose threads are hidden in the thread tree.
+    collapsed_threads: HashSet<u32>,
+    /// The row last clicked.
+    selected: Option<ProcTarget>,
+    /// A signal waiting for confirmation.
+    pending_signal: Option<(ProcTarget, Signal)>,
+    /// The outcome of the last action, shown until dismissed.
+    message: Option<String>,
+    /// The open priority and affinity window.
+    actions: Option<Actions>,
+    /// The pid whose detail page is shown instead of the table.
+    detail: Option<u32>,
+    detail_pane: DetailPane,
+    open_files: Option<OnDemand<Vec<OpenFile>>>,
+    memory_maps: Option<OnDemand<MemoryMaps>>,
+    /// Read RSS, PSS, dirty and swap of every mapping from the slower `smaps`.
+    smaps: bool,
+    /// Sum the mappings of each backing file instead of listing them.
+    maps_by_file: bool,
+    /// The query of the search bar, as parsed into `ProcInfo`'s filter.
+    search: String,
+    /// Why `search` failed to parse, in which case the previous filter is kept.
+    search_error: Option<String>,
+    /// Application icons, decoded on first use. `None` if the file could not be decoded.
+    icons: HashMap<PathBuf, Option<egui::TextureHandle>>,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DetailPane {
+    History,
+    OpenFiles,
+    MemoryMaps,
+}
+/// Details read only while shown, and then at most once per second. Read on a thread of their
+/// own, as those of large processes take long enough to stall rendering.
+struct OnDemand<T> {
+    pid: u32,
+    /// When `pid` was last sent to be read.
+    read_at: Option<f64>,
+    /// Whether a read is in progress, of `pid` or of the one before.
+    reading: bool,
+    /// `None` until the first read of `pid` arrives, and then `None` within if the process has
+    /// exited or is not ours to inspect.
+    value: Option<Option<T>>,
+    reads: Sender<u32>,
+    values: Receiver<(u32, Option<T>)>,
+}
+/// The mappings of a process, along with their sums by backing file, which take a while to
+/// compute for large processes.
+struct MemoryMaps {
+    maps: Vec<MemoryMap>,
+    by_file: Vec<MapRollup>,
+}
+/// The values being edited in the actions window, initially those of the target's main thread.
+struct Actions {
+    target: ProcTarget,
+    /// Whether to apply to every thread rather than just the main thread of each process.
+    all_threads: bool,
+    nice: i8,
+    io_priority: IoPriority,
+    policy: SchedPolicy,
+    rt_priority: u8,
+    affinity: String,
+}
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ProcessNavigation {
     LoginSessions,
     Sessions,
     Processes,
     Threads,
+    Cgroups,
+    Services,
+    Users,
+    Groups,
+    Containers,
+    Apps,
+    Exited,
+}
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Column {
+    UserCpu,
+    SysCpu,
+    ChildrenCpu,
+    DiskRead,
+    DiskWrite,
+    NetSend,
+    NetReceive,
+    Mem,
+    Pss,
+    Uss,
+    RssAnon,
+    RssFile,
+    RssShmem,
+    Swap,
+    MinorFaults,
+    MajorFaults,
+    VoluntarySwitches,
+    InvoluntarySwitches,
+    RunDelay,
+    Gpu,
+    Vram,
+    CgroupCpu,
+    CgroupMem,
+    CgroupRead,
+    CgroupWrite,
+    CgroupPids,
+    Unit,
+    User,
+    Nice,
+    Policy,
+    IoPriority,
+    Affinity,
+    State,
+    StartTime,
+    Elapsed,
+    Tty,
+    Ppid,
+    NsPid,
+    Threads,
+}
+impl ProcessTabState {
+    pub fn new(nav: ProcessNavigation, show_pss: bool) -> Self {
+        let mut columns: BTreeSet<Column> = Column::DEFAULT.into_iter().collect();
+        if show_pss {
+            columns.insert(Column::Pss);
+        }
+        Self {
+            nav,
+            columns,
+            pss: show_pss,
+            tree: false,
+            collapsed: HashSet::new(),
+            collapsed_threads: HashSet::new(),
+            selected: None,
+            pending_signal: None,
+            message: None,
+            actions: None,
+            detail: None,
+            detail_pane: DetailPane::History,
+            open_files: None,
+            memory_maps: None,
+            smaps: true,
+            maps_by_file: true,
+            search: String::new(),
+            search_error: None,
+            icons: HashMap::new(),
+        }
+    }
 }
 impl Component for ProcessTab {
-    type Navigation = ProcessNavigation;
+    type Navigation = ProcessTabState;
     type Info = ProcInfo;
-    fn render(ui: &mut Ui, nav: &mut Self::Navigation, info: &mut Self::Info) {
+    fn render(ui: &mut Ui, state: &mut Self::Navigation, info: &mut Self::Info) {
+        let old_nav = state.nav;
+        let nav = &mut state.nav;
+        let typing = ui.ctx().wants_keyboard_input();
+        let mut focus_search = false;
         ui.ctx().input_mut(|i| {
+            if typing {
+                // Keys belong to the focused text field
+                return;
+            }
             if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::L)) {
                 *nav = ProcessNavigation::LoginSessions;
             } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::S)) {
@@ -25,6 +199,35 @@ impl Component for ProcessTab {
                 *nav = ProcessNavigation::Processes;
             } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::T)) {
                 *nav = ProcessNavigation::Threads;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::H)) {
+                *nav = ProcessNavigation::Cgroups;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::V)) {
+                *nav = ProcessNavigation::Services;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::U)) {
+                *nav = ProcessNavigation::Users;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::O)) {
+                *nav = ProcessNavigation::Groups;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::X)) {
+                *nav = ProcessNavigation::Containers;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::D)) {
+                *nav = ProcessNavigation::Apps;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::E)) {
+                *nav = ProcessNavigation::Exited;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::F)) {
+                if *nav != ProcessNavigation::Threads {
+                    *nav = ProcessNavigation::Processes;
+                }
+                state.tree = !state.tree;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::Delete)) {
+                state.pending_signal = state.selected.clone().map(|t| (t, Signal::SIGTERM));
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::A)) {
+                state.actions = state.selected.clone().map(|t| Actions::new(t, info));
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::Escape)) {
+                state.pending_signal = None;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::Backspace)) {
+                state.detail = None;
+            } else if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::Slash)) {
+                focus_search = true;
             }
             if i.consume_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::I)) {
                 info.sort(ProcSortBy::Id);
@@ -46,68 +249,1037 @@ impl Component for ProcessTab {
             ui.selectable_value(nav, ProcessNavigation::Sessions, "Sessions (s)");
             ui.selectable_value(nav, ProcessNavigation::Processes, "Processes (p)");
             ui.selectable_value(nav, ProcessNavigation::Threads, "Threads (t)");
+            ui.selectable_value(nav, ProcessNavigation::Cgroups, "Cgroups (h)");
+            ui.selectable_value(nav, ProcessNavigation::Services, "Services (v)");
+            ui.selectable_value(nav, ProcessNavigation::Users, "Users (u)");
+            ui.selectable_value(nav, ProcessNavigation::Groups, "Groups (o)");
+            ui.selectable_value(nav, ProcessNavigation::Containers, "Containers (x)");
+            ui.selectable_value(nav, ProcessNavigation::Apps, "Apps (d)");
+            ui.selectable_value(nav, ProcessNavigation::Exited, "Exited (e)");
+            ui.separator();
+            if matches!(
+                *nav,
+                ProcessNavigation::Processes | ProcessNavigation::Threads
+            ) {
+                ui.checkbox(&mut state.tree, "Tree (f)");
+            }
+            ui.menu_button("Columns", |ui| {
+                for column in Column::ALL
+                    .into_iter()
+                    .filter(|c| state.pss || !matches!(c, Column::Pss | Column::Uss))
+                {
+                    let mut shown = state.columns.contains(&column);
+                    if ui.checkbox(&mut shown, column.title()).changed() {
+                        match shown {
+                            true => state.columns.insert(column),
+                            false => state.columns.remove(&column),
+                        };
+                    }
+                }
+            });
+            ui.separator();
+            let search = ui.add(
+                egui::TextEdit::singleline(&mut state.search)
+                    .hint_text("Search (/), e.g. user:root cpu>5%")
+                    .desired_width(240.0),
+            );
+            if focus_search {
+                search.request_focus();
+            }
+            if search.changed() {
+                match ProcFilter::parse(&state.search) {
+                    Ok(filter) => {
+                        info.filter(filter);
+                        state.search_error = None;
+                    }
+                    Err(err) => state.search_error = Some(err),
+                }
+            }
+            if let Some(err) = &state.search_error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+            if let Some(message) = &state.message {
+                ui.separator();
+                ui.label(message);
+                if ui.small_button("x").clicked() {
+                    state.message = None;
+                }
+            }
         });
+        if *nav != old_nav {
+            state.detail = None;
+        }
+        if let Some(pid) = state.detail {
+            detail_page(ui, state, info, pid);
+            confirm_signal(ui, state, info);
+            actions_window(ui, state, info);
+            return;
+        }
+        if *nav == ProcessNavigation::Exited {
+            exited_table(ui, info);
+            return;
+        }
+
+        let columns: Vec<Column> = state
+            .columns
+            .iter()
+            .copied()
+            .filter(|column| column.applies_to(*nav))
+            .collect();
 
         let mut sort_by = info.get_sort_by();
-        match nav {
-            ProcessNavigation::LoginSessions => {
-                Table {
-                    id_header: "Lsid",
-                    sort_by: &mut sort_by,
-                    rows: info.login_sessions.iter().map(|ls| Row {
-                        id: format!("{:?}", ls.lsid),
-                        name: &ls.name,
-                        hover_name: None,
-                        stat: ls.stat,
-                    }),
+        let selected = state.selected.as_ref();
+        let affinity = |sched: SchedInfo| sched.affinity.map_or("?", |h| info.strings.get(h));
+        let user = |uid: u32| Some((uid, info.user(uid)));
+        if *nav == ProcessNavigation::Apps {
+            for path in info.apps.iter().filter_map(|app| app.icon.as_ref()) {
+                if !state.icons.contains_key(path) {
+                    let icon = load_icon(ui.ctx(), path);
+                    state.icons.insert(path.clone(), icon);
                 }
-                .render(ui);
             }
-            ProcessNavigation::Sessions => {
-                Table {
-                    id_header: "Sid",
+        }
+        let resp = match nav {
+            ProcessNavigation::LoginSessions => Table {
+                columns: &columns,
+                selected,
+                id_header: "Lsid",
+                sort_by: &mut sort_by,
+                rows: info.login_sessions.iter().map(|ls| Row {
+                    id: format!("{:?}", ls.lsid),
+                    name: &ls.name,
+                    hover_name: None,
+                    stat: ls.stat,
+                    cgroup_stat: None,
+                    icon: None,
+                    user: None,
+                    ns_pid: None,
+                    unit: None,
+                    task: None,
+                    sched: None,
+                    tree: None,
+                    target: Some(ProcTarget::LoginSession(ls.lsid)),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Sessions => Table {
+                columns: &columns,
+                selected,
+                id_header: "Sid",
+                sort_by: &mut sort_by,
+                rows: info.sessions.iter().map(|s| Row {
+                    id: format!("{:?}", s.sid),
+                    name: info.strings.get(s.name),
+                    hover_name: (!s.entries_cmdline.is_empty()).then_some(&s.entries_cmdline),
+                    stat: s.stat,
+                    cgroup_stat: None,
+                    icon: None,
+                    user: None,
+                    ns_pid: None,
+                    unit: None,
+                    task: None,
+                    sched: None,
+                    tree: None,
+                    target: Some(ProcTarget::Session(s.sid)),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Processes if state.tree => {
+                let collapsed = &mut state.collapsed;
+                collapsed.retain(|&pid| info.processes.iter().any(|p| p.pid == pid));
+                let mut visible = Vec::new();
+                let mut i = 0;
+                while let Some(entry) = info.process_tree.get(i) {
+                    visible.push(entry);
+                    i += 1;
+                    if collapsed.contains(&info.processes[entry.idx].pid) {
+                        i += entry.num_descendants;
+                    }
+                }
+                let resp = Table {
+                    columns: &columns,
+                    selected,
+                    id_header: "Pid",
                     sort_by: &mut sort_by,
-                    rows: info.sessions.iter().map(|s| Row {
-                        id: format!("{:?}", s.sid),
-                        name: info.strings.get(s.name),
-                        hover_name: (!s.entries_cmdline.is_empty()).then_some(&s.entries_cmdline),
-                        stat: s.stat,
+                    rows: visible.iter().map(|entry| {
+                        let p = &info.processes[entry.idx];
+                        let is_collapsed = collapsed.contains(&p.pid);
+                        Row {
+                            id: format!("{:?}", p.pid),
+                            name: info.strings.get(p.name),
+                            hover_name: p.cmdline.as_deref(),
+                            // Collapsed rows stand in for their hidden descendants
+                            stat: match is_collapsed {
+                                true => entry.subtree_stat,
+                                false => p.stat,
+                            },
+                            cgroup_stat: None,
+                            icon: None,
+                            user: user(p.uid),
+                            ns_pid: p.ns_pid,
+                            unit: p.unit.as_deref(),
+                            task: Some(p.task),
+                            sched: Some((p.sched, affinity(p.sched))),
+                            tree: Some(TreeCell {
+                                depth: entry.depth,
+                                collapsed: (entry.num_descendants > 0).then_some(is_collapsed),
+                            }),
+                            target: Some(ProcTarget::Process(p.pid)),
+                        }
                     }),
                 }
                 .render(ui);
+                if let Some(ProcTarget::Process(pid)) = resp.toggled {
+                    if !collapsed.remove(&pid) {
+                        collapsed.insert(pid);
+                    }
+                }
+                resp
             }
-            ProcessNavigation::Processes => {
-                Table {
-                    id_header: "Pid",
+            ProcessNavigation::Processes => Table {
+                columns: &columns,
+                selected,
+                id_header: "Pid",
+                sort_by: &mut sort_by,
+                rows: info.processes.iter().map(|p| Row {
+                    id: format!("{:?}", p.pid),
+                    name: info.strings.get(p.name),
+                    hover_name: p.cmdline.as_deref(),
+                    stat: p.stat,
+                    cgroup_stat: None,
+                    icon: None,
+                    user: user(p.uid),
+                    ns_pid: p.ns_pid,
+                    unit: p.unit.as_deref(),
+                    task: Some(p.task),
+                    sched: Some((p.sched, affinity(p.sched))),
+                    tree: None,
+                    target: Some(ProcTarget::Process(p.pid)),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Threads if state.tree => {
+                let collapsed = &mut state.collapsed_threads;
+                collapsed.retain(|&pid| info.processes.iter().any(|p| p.pid == pid));
+                // Both in the current sort order
+                let mut threads_by_pid: HashMap<u32, Vec<&ThreadInfo>> = HashMap::new();
+                for t in &info.threads {
+                    threads_by_pid.entry(t.parent_pid).or_default().push(t);
+                }
+                let groups: Vec<(&ProcessInfo, String, &[&ThreadInfo])> = info
+                    .processes
+                    .iter()
+                    .map(|p| {
+                        let threads = threads_by_pid.get(&p.pid).map_or(&[][..], Vec::as_slice);
+                        let name = info.strings.get(p.name);
+                        let label = match threads.len() {
+                            1 => name.to_owned(),
+                            n => format!("{name} ({n} threads)"),
+                        };
+                        (p, label, threads)
+                    })
+                    .collect();
+                let resp = Table {
+                    columns: &columns,
+                    selected,
+                    id_header: "Pid/tid",
                     sort_by: &mut sort_by,
-                    rows: info.processes.iter().map(|p| Row {
-                        id: format!("{:?}", p.pid),
-                        name: info.strings.get(p.name),
-                        hover_name: p.cmdline.as_deref(),
-                        stat: p.stat,
+                    rows: groups.iter().flat_map(|(p, label, threads)| {
+                        let is_collapsed = collapsed.contains(&p.pid);
+                        let process_row = Row {
+                            id: format!("{:?}", p.pid),
+                            name: label,
+                            hover_name: p.cmdline.as_deref(),
+                            stat: p.stat,
+                            cgroup_stat: None,
+                            icon: None,
+                            user: user(p.uid),
+                            ns_pid: p.ns_pid,
+                            unit: p.unit.as_deref(),
+                            task: Some(p.task),
+                            sched: Some((p.sched, affinity(p.sched))),
+                            tree: Some(TreeCell {
+                                depth: 0,
+                                collapsed: Some(is_collapsed),
+                            }),
+                            target: Some(ProcTarget::Process(p.pid)),
+                        };
+                        let shown: &[&ThreadInfo] = match is_collapsed {
+                            true => &[],
+                            false => threads,
+                        };
+                        let thread_rows = shown.iter().map(|t| Row {
+                            id: format!("{:?}", t.tid),
+                            name: info.strings.get(t.name),
+                            hover_name: None,
+                            stat: t.stat,
+                            cgroup_stat: None,
+                            icon: None,
+                            user: user(p.uid),
+                            ns_pid: None,
+                            unit: p.unit.as_deref(),
+                            task: Some(t.task),
+                            sched: Some((t.sched, affinity(t.sched))),
+                            tree: Some(TreeCell {
+                                depth: 1,
+                                collapsed: None,
+                            }),
+                            target: None,
+                        });
+                        std::iter::once(process_row).chain(thread_rows)
                     }),
                 }
                 .render(ui);
+                if let Some(ProcTarget::Process(pid)) = resp.toggled {
+                    if !collapsed.remove(&pid) {
+                        collapsed.insert(pid);
+                    }
+                }
+                resp
             }
             ProcessNavigation::Threads => {
+                let process_by_pid: HashMap<u32, &ProcessInfo> =
+                    info.processes.iter().map(|p| (p.pid, p)).collect();
                 Table {
+                    columns: &columns,
+                    selected,
                     id_header: "Tid",
                     sort_by: &mut sort_by,
-                    rows: info.threads.iter().map(|t| Row {
-                        id: format!("{:?}", t.tid),
-                        name: info.strings.get(t.name),
-                        hover_name: None,
-                        stat: t.stat,
+                    rows: info.threads.iter().map(|t| {
+                        let process = process_by_pid.get(&t.parent_pid);
+                        Row {
+                            id: format!("{:?}", t.tid),
+                            name: info.strings.get(t.name),
+                            hover_name: None,
+                            stat: t.stat,
+                            cgroup_stat: None,
+                            icon: None,
+                            user: process.and_then(|p| user(p.uid)),
+                            ns_pid: None,
+                            unit: process.and_then(|p| p.unit.as_deref()),
+                            task: Some(t.task),
+                            sched: Some((t.sched, affinity(t.sched))),
+                            tree: None,
+                            target: None,
+                        }
                     }),
                 }
-                .render(ui);
+                .render(ui)
             }
-        }
+            ProcessNavigation::Cgroups => Table {
+                columns: &columns,
+                selected,
+                id_header: "Cgroup id",
+                sort_by: &mut sort_by,
+                rows: info.cgroups.iter().map(|cg| Row {
+                    id: format!("{:?}", cg.id),
+                    name: &cg.path,
+                    hover_name: None,
+                    stat: cg.stat,
+                    cgroup_stat: Some(&cg.kernel_stat),
+                    icon: None,
+                    user: None,
+                    ns_pid: None,
+                    unit: None,
+                    task: None,
+                    sched: None,
+                    tree: None,
+                    target: Some(ProcTarget::Cgroup(cg.path.clone())),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Services => Table {
+                columns: &columns,
+                selected,
+                id_header: "Depth",
+                sort_by: &mut sort_by,
+                rows: info.units.iter().map(|u| Row {
+                    id: u.depth.to_string(),
+                    name: &u.name,
+                    hover_name: Some(&u.path),
+                    stat: u.stat,
+                    cgroup_stat: None,
+                    icon: None,
+                    user: None,
+                    ns_pid: None,
+                    unit: None,
+                    task: None,
+                    sched: None,
+                    tree: Some(TreeCell {
+                        depth: u.depth,
+                        collapsed: None,
+                    }),
+                    target: Some(ProcTarget::Unit(u.path.clone())),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Users => Table {
+                columns: &columns,
+                selected,
+                id_header: "Uid",
+                sort_by: &mut sort_by,
+                rows: info.users.iter().map(|u| Row {
+                    id: format!("{:?}", u.uid),
+                    name: &u.name,
+                    hover_name: info
+                        .user(u.uid)
+                        .map(|user| user.description.as_str())
+                        .filter(|description| !description.is_empty()),
+                    stat: u.stat,
+                    cgroup_stat: None,
+                    icon: None,
+                    user: None,
+                    ns_pid: None,
+                    unit: None,
+                    task: None,
+                    sched: None,
+                    tree: None,
+                    target: Some(ProcTarget::User(u.uid)),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Groups => Table {
+                columns: &columns,
+                selected,
+                id_header: "Gid",
+                sort_by: &mut sort_by,
+                rows: info.groups.iter().map(|g| Row {
+                    id: format!("{:?}", g.gid),
+                    name: &g.name,
+                    hover_name: None,
+                    stat: g.stat,
+                    cgroup_stat: None,
+                    icon: None,
+                    user: None,
+                    ns_pid: None,
+                    unit: None,
+                    task: None,
+                    sched: None,
+                    tree: None,
+                    target: Some(ProcTarget::Group(g.gid)),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Containers => {
+                let labels: Vec<(String, String)> = info
+                    .containers
+                    .iter()
+                    .map(|c| {
+                        let name = c.hostname.as_deref().unwrap_or(c.id.short_id());
+                        let mut hover = format!("{} {}", c.id.runtime, c.id.id);
+                        if let Some(ns) = c.namespaces {
+                            hover += &format!(
+                                "\nnamespaces: pid {} net {} mnt {} user {} uts {}",
+                                ns.pid, ns.net, ns.mnt, ns.user, ns.uts
+                            );
+                        }
+                        (format!("{name} ({})", c.id.runtime), hover)
+                    })
+                    .collect();
+                Table {
+                    columns: &columns,
+                    selected,
+                    id_header: "Container",
+                    sort_by: &mut sort_by,
+                    rows: info
+                        .containers
+                        .iter()
+                        .zip(&labels)
+                        .map(|(c, (name, hover))| Row {
+                            id: c.id.short_id().to_owned(),
+                            name,
+                            hover_name: Some(hover),
+                            stat: c.stat,
+                            cgroup_stat: None,
+                            icon: None,
+                            user: None,
+                            ns_pid: None,
+                            unit: None,
+                            task: None,
+                            sched: None,
+                            tree: None,
+                            target: Some(ProcTarget::Container(c.id.clone())),
+                        }),
+                }
+                .render(ui)
+            }
+            ProcessNavigation::Apps => Table {
+                columns: &columns,
+                selected,
+                id_header: "App",
+                sort_by: &mut sort_by,
+                rows: info.apps.iter().map(|app| Row {
+                    id: app.id.clone(),
+                    name: &app.name,
+                    hover_name: None,
+                    stat: app.stat,
+                    cgroup_stat: None,
+                    icon: app
+                        .icon
+                        .as_ref()
+                        .and_then(|path| state.icons.get(path)?.as_ref())
+                        .map(|texture| texture.id()),
+                    user: None,
+                    ns_pid: None,
+                    unit: None,
+                    task: None,
+                    sched: None,
+                    tree: None,
+                    target: Some(ProcTarget::App(app.id.clone())),
+                }),
+            }
+            .render(ui),
+            ProcessNavigation::Exited => unreachable!(),
+        };
         info.sort(sort_by);
+        if let Some(target) = resp.clicked {
+            state.selected = Some(target);
+        }
+        if let Some(pending) = resp.signal {
+            state.pending_signal = Some(pending);
+        }
+        if let Some(target) = resp.actions {
+            state.actions = Some(Actions::new(target, info));
+        }
+        if let Some(ProcTarget::Process(pid)) = resp.opened {
+            state.selected = Some(ProcTarget::Process(pid));
+            state.detail = Some(pid);
+        }
+        confirm_signal(ui, state, info);
+        actions_window(ui, state, info);
+    }
+}
+
+/// Decodes a PNG file into a texture, or `None` if it cannot be read.
+fn load_icon(ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+    let mut decoder = png::Decoder::new(File::open(path).ok()?);
+    // Expands palettes and bit depths other than 8
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).ok()?;
+    let pixels = &buf[..frame.buffer_size()];
+    let rgba: Vec<u8> = match frame.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return None,
+    };
+    let size = [frame.width as usize, frame.height as usize];
+    let image = egui::ColorImage::from_rgba_unmultiplied(size, &rgba);
+    Some(ctx.load_texture(path.to_string_lossy(), image, egui::TextureOptions::LINEAR))
+}
+fn exited_table(ui: &mut Ui, info: &ProcInfo) {
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        crate::vim_like_scroll(
+            ui,
+            2.0 * ui.text_style_height(&TextStyle::Body),
+            20.0 * ui.text_style_height(&TextStyle::Body),
+        );
+        egui::Grid::new("exited")
+            .num_columns(10)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in [
+                    "Pid", "Name", "User", "Started", "Exited", "Lifetime", "Cpu", "Peak mem",
+                    "Read", "Written",
+                ] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                // Most recent first
+                for p in info.exited.iter().rev() {
+                    ui.label(p.pid.to_string());
+                    let name = ui.label(&p.name);
+                    if let Some(cmdline) = &p.cmdline {
+                        name.on_hover_text(cmdline);
+                    }
+                    ui.label(info.user_name(p.uid));
+                    ui.label(p.format_start_time());
+                    ui.label(p.format_end_time());
+                    ui.label(p.format_lifetime());
+                    ui.label(format!("{:.2}s", p.cpu_time_ms as f64 / 1000.0));
+                    ui.label(Show::size(p.peak_mem_bytes as f64));
+                    ui.label(Show::size(p.read_bytes as f64));
+                    ui.label(Show::size(p.write_bytes as f64));
+                    ui.end_row();
+                }
+            });
+    });
+}
+fn detail_page(ui: &mut Ui, state: &mut ProcessTabState, info: &ProcInfo, pid: u32) {
+    let process = info.processes.iter().find(|p| p.pid == pid);
+    ui.horizontal(|ui| {
+        if ui.button("Back (backspace)").clicked() {
+            state.detail = None;
+        }
+        ui.heading(match process {
+            Some(p) => format!("{} ({pid})", info.strings.get(p.name)),
+            None => format!("Process {pid} has exited"),
+        });
+        ui.separator();
+        ui.selectable_value(&mut state.detail_pane, DetailPane::History, "History");
+        ui.selectable_value(&mut state.detail_pane, DetailPane::OpenFiles, "Open files");
+        ui.selectable_value(
+            &mut state.detail_pane,
+            DetailPane::MemoryMaps,
+            "Memory maps",
+        );
+    });
+    let (Some(process), Some(history)) = (process, info.history_by_pid.get(&pid)) else {
+        return;
+    };
+    if let Some(cmdline) = &process.cmdline {
+        ui.monospace(cmdline);
+    }
+    let now = ui.input(|i| i.time);
+    match state.detail_pane {
+        DetailPane::History => history_pane(ui, history),
+        DetailPane::OpenFiles => {
+            let open_files = state.open_files.get_or_insert_with(|| {
+                OnDemand::start(ui.ctx(), "open-files", procinfo::get_open_files)
+            });
+            match open_files.get(pid, now) {
+                Some(Some(files)) => open_files_pane(ui, files),
+                Some(None) => {
+                    ui.label("Cannot read the open files of this process");
+                }
+                None => {
+                    ui.spinner();
+                }
+            }
+        }
+        DetailPane::MemoryMaps => {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut state.maps_by_file, true, "By file");
+                ui.selectable_value(&mut state.maps_by_file, false, "Mappings");
+                ui.separator();
+                if ui
+                    .checkbox(&mut state.smaps, "Memory usage (smaps)")
+                    .changed()
+                {
+                    state.memory_maps = None;
+                }
+            });
+            let smaps = state.smaps;
+            let memory_maps = state.memory_maps.get_or_insert_with(|| {
+                OnDemand::start(ui.ctx(), "memory-maps", move |pid| {
+                    let maps = procinfo::get_memory_maps(pid, smaps)?;
+                    let by_file = procinfo::rollup_memory_maps(&maps);
+                    Some(MemoryMaps { maps, by_file })
+                })
+            });
+            match memory_maps.get(pid, now) {
+                Some(Some(maps)) => memory_maps_pane(ui, maps, state.maps_by_file),
+                Some(None) => {
+                    ui.label("Cannot read the memory maps of this process");
+                }
+                None => {
+                    ui.spinner();
+                }
+            }
+        }
+    }
+}
+fn history_pane(ui: &mut Ui, history: &ProcessHistory) {
+    let width = ui.available_width() / 2.0 - ui.style().spacing.item_spacing.x;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("process-detail")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (i, (name, series, value_kind)) in [
+                    ("CPU", &history.cpu, ValueKind::Percent),
+                    ("Memory", &history.mem_bytes, ValueKind::Bytes),
+                    (
+                        "Disk read",
+                        &history.disk_read_bytes_per_second,
+                        ValueKind::Bytes,
+                    ),
+                    (
+                        "Disk write",
+                        &history.disk_write_bytes_per_second,
+                        ValueKind::Bytes,
+                    ),
+                    (
+                        "Net send",
+                        &history.net_send_bytes_per_second,
+                        ValueKind::Bytes,
+                    ),
+                    (
+                        "Net receive",
+                        &history.net_receive_bytes_per_second,
+                        ValueKind::Bytes,
+                    ),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    TimeSeries {
+                        name,
+                        max_y: None,
+                        kind: TimeSeriesKind::GridCell { width },
+                        value_kind,
+                    }
+                    .render(ui, &[(name, series)]);
+                    if i % 2 == 1 {
+                        ui.end_row();
+                    }
+                }
+            });
+    });
+}
+fn open_files_pane(ui: &mut Ui, files: &[OpenFile]) {
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        crate::vim_like_scroll(
+            ui,
+            2.0 * ui.text_style_height(&TextStyle::Body),
+            20.0 * ui.text_style_height(&TextStyle::Body),
+        );
+        egui::Grid::new("open-files")
+            .num_columns(6)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Fd", "Kind", "Target", "Flags", "Pos", "Socket"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for file in files {
+                    ui.label(file.fd.to_string());
+                    ui.label(file.kind.to_string());
+                    ui.monospace(&file.target);
+                    ui.label(file.describe_flags());
+                    ui.label(
+                        file.pos
+                            .map_or_else(|| "?".to_owned(), |pos| pos.to_string()),
+                    );
+                    ui.monospace(match &file.socket {
+                        Some(socket) if socket.remote.is_empty() => {
+                            format!("{} {} {}", socket.protocol, socket.local, socket.state)
+                        }
+                        Some(socket) => format!(
+                            "{} {} -> {} {}",
+                            socket.protocol, socket.local, socket.remote, socket.state
+                        ),
+                        None => String::new(),
+                    });
+                    ui.end_row();
+                }
+            });
+    });
+}
+fn memory_maps_pane(ui: &mut Ui, maps: &MemoryMaps, by_file: bool) {
+    fn usage_cells(ui: &mut Ui, usage: Option<MapUsage>) {
+        let usage = usage.unwrap_or_default();
+        for bytes in [
+            usage.rss_bytes,
+            usage.pss_bytes,
+            usage.dirty_bytes,
+            usage.swap_bytes,
+        ] {
+            ui.label(Show::size(bytes as f64));
+        }
+    }
+    fn path_of(path: &str) -> &str {
+        match path {
+            "" => "[anon]",
+            path => path,
+        }
+    }
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        crate::vim_like_scroll(
+            ui,
+            2.0 * ui.text_style_height(&TextStyle::Body),
+            20.0 * ui.text_style_height(&TextStyle::Body),
+        );
+        let usage_headers = ["RSS", "PSS", "Dirty", "Swap"];
+        match by_file {
+            true => {
+                egui::Grid::new("memory-maps-by-file")
+                    .num_columns(7)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["File", "Mappings", "Size"].iter().chain(&usage_headers) {
+                            ui.strong(*header);
+                        }
+                        ui.end_row();
+                        for rollup in &maps.by_file {
+                            ui.monospace(path_of(&rollup.path));
+                            ui.label(rollup.mappings.to_string());
+                            ui.label(Show::size(rollup.size_bytes as f64));
+                            usage_cells(ui, rollup.usage);
+                            ui.end_row();
+                        }
+                    });
+            }
+            false => {
+                egui::Grid::new("memory-maps")
+                    .num_columns(9)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["Address", "Perms", "Offset", "Size"]
+                            .iter()
+                            .chain(&usage_headers)
+                            .chain(&["File"])
+                        {
+                            ui.strong(*header);
+                        }
+                        ui.end_row();
+                        for map in &maps.maps {
+                            ui.monospace(format!("{:012x}-{:012x}", map.start, map.end));
+                            ui.monospace(&map.perms);
+                            ui.monospace(format!("{:x}", map.offset));
+                            ui.label(Show::size((map.end - map.start) as f64));
+                            usage_cells(ui, map.usage);
+                            ui.monospace(path_of(&map.path));
+                            ui.end_row();
+                        }
+                    });
+            }
+        }
+    });
+}
+impl<T: Send + 'static> OnDemand<T> {
+    fn start(
+        ctx: &egui::Context,
+        name: &str,
+        read: impl Fn(u32) -> Option<T> + Send + 'static,
+    ) -> Self {
+        let (reads, read_rx) = mpsc::channel();
+        let (value_tx, values) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::Builder::new()
+            .name(name.to_owned())
+            .spawn(move || {
+                // Ends once the pane's state is dropped
+                for pid in read_rx {
+                    if value_tx.send((pid, read(pid))).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+            })
+            .unwrap();
+        Self {
+            pid: 0,
+            read_at: None,
+            reading: false,
+            value: None,
+            reads,
+            values,
+        }
+    }
+    /// Returns `None` until the first read of `pid` arrives, without waiting for it.
+    fn get(&mut self, pid: u32, now: f64) -> Option<Option<&T>> {
+        for (read_pid, value) in self.values.try_iter() {
+            self.reading = false;
+            if read_pid == self.pid {
+                self.value = Some(value);
+            }
+        }
+        if pid != self.pid {
+            self.pid = pid;
+            self.read_at = None;
+            self.value = None;
+        }
+        if !self.reading && self.read_at.is_none_or(|read_at| now - read_at >= 1.0) {
+            // Should the thread have panicked, the last value is kept
+            self.reading = self.reads.send(pid).is_ok();
+            self.read_at = Some(now);
+        }
+        self.value.as_ref().map(Option::as_ref)
+    }
+}
+fn confirm_signal(ui: &mut Ui, state: &mut ProcessTabState, info: &ProcInfo) {
+    let Some((target, signal)) = state.pending_signal.clone() else {
+        return;
+    };
+    let mut processes = info.identities_of(&target);
+    if !matches!(target, ProcTarget::Process(_)) {
+        // Fanning out to a group should not stop or kill pi itself
+        processes.retain(|&(pid, _)| pid != std::process::id());
+    }
+    let description = info.describe(&target);
+    egui::Window::new("Send signal")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ui.ctx(), |ui| {
+            ui.label(format!("Send {} to {description}?", signal.as_str()));
+            if processes.len() != 1 {
+                ui.label(format!(
+                    "This signals {} processes, including any hidden by the search.",
+                    processes.len()
+                ));
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Send").clicked() {
+                    let failed = procinfo::send_signal(&processes, signal);
+                    state.message = Some(outcome_message(
+                        &format!("{} to {description}", signal.as_str()),
+                        processes.len(),
+                        &failed,
+                    ));
+                    state.pending_signal = None;
+                }
+                if ui.button("Cancel").clicked() {
+                    state.pending_signal = None;
+                }
+            });
+        });
+}
+fn actions_window(ui: &mut Ui, state: &mut ProcessTabState, info: &ProcInfo) {
+    let Some(actions) = state.actions.as_mut() else {
+        return;
+    };
+    let mut open = true;
+    let mut message = None;
+    egui::Window::new("Priority and affinity")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ui.ctx(), |ui| {
+            let description = info.describe(&actions.target);
+            ui.label(&description);
+            ui.checkbox(&mut actions.all_threads, "All threads");
+            let ids = || match actions.all_threads {
+                true => info.tids_of(&actions.target),
+                false => info.pids_of(&actions.target),
+            };
+            egui::Grid::new("actions").num_columns(3).show(ui, |ui| {
+                ui.label("Nice");
+                ui.add(egui::DragValue::new(&mut actions.nice).clamp_range(-20..=19));
+                if ui.button("Set").clicked() {
+                    let ids = ids();
+                    let failed = procinfo::set_nice(&ids, actions.nice);
+                    let action = format!("Nice {} for {description}", actions.nice);
+                    message = Some(outcome_message(&action, ids.len(), &failed));
+                }
+                ui.end_row();
+
+                ui.label("I/O priority");
+                ui.horizontal(|ui| {
+                    let class = &mut actions.io_priority.class;
+                    egui::ComboBox::from_id_source("io-class")
+                        .selected_text(class.to_string())
+                        .show_ui(ui, |ui| {
+                            for c in IoClass::ALL {
+                                ui.selectable_value(class, c, c.to_string());
+                            }
+                        });
+                    if class.has_level() {
+                        ui.add(
+                            egui::DragValue::new(&mut actions.io_priority.level).clamp_range(0..=7),
+                        );
+                    }
+                });
+                if ui.button("Set").clicked() {
+                    let ids = ids();
+                    let failed = procinfo::set_io_priority(&ids, actions.io_priority);
+                    let action = format!("I/O priority {} for {description}", actions.io_priority);
+                    message = Some(outcome_message(&action, ids.len(), &failed));
+                }
+                ui.end_row();
+
+                ui.label("Policy");
+                ui.horizontal(|ui| {
+                    let policy = &mut actions.policy;
+                    egui::ComboBox::from_id_source("sched-policy")
+                        .selected_text(policy.to_string())
+                        .show_ui(ui, |ui| {
+                            for p in SchedPolicy::SETTABLE {
+                                ui.selectable_value(policy, p, p.to_string());
+                            }
+                        });
+                    if policy.is_realtime() {
+                        ui.add(egui::DragValue::new(&mut actions.rt_priority).clamp_range(1..=99));
+                    }
+                });
+                if ui.button("Set").clicked() {
+                    let policy = actions.policy;
+                    let rt_priority = match policy.is_realtime() {
+                        true => actions.rt_priority.clamp(1, 99),
+                        false => 0,
+                    };
+                    let ids = ids();
+                    let failed = procinfo::set_sched_policy(&ids, policy, rt_priority);
+                    let action = format!("Policy {policy} for {description}");
+                    message = Some(outcome_message(&action, ids.len(), &failed));
+                }
+                ui.end_row();
+
+                ui.label("Affinity");
+                ui.text_edit_singleline(&mut actions.affinity);
+                if ui.button("Set").clicked() {
+                    message = Some(match procinfo::parse_cpu_list(&actions.affinity) {
+                        Some(cpus) => {
+                            let ids = ids();
+                            let failed = procinfo::set_affinity(&ids, &cpus);
+                            let action = format!("Affinity {} for {description}", actions.affinity);
+                            outcome_message(&action, ids.len(), &failed)
+                        }
+                        None => format!("Invalid CPU list {:?}", actions.affinity),
+                    });
+                }
+                ui.end_row();
+            });
+        });
+    if message.is_some() {
+        state.message = message;
+    }
+    if !open {
+        state.actions = None;
+    }
+}
+/// Summarizes the result of applying `action` to `total` pids or tids.
+fn outcome_message(action: &str, total: usize, failed: &[(u32, Errno)]) -> String {
+    match failed.is_empty() {
+        true => format!("{action} succeeded"),
+        false => format!(
+            "{action} failed for {} of {total}: {}",
+            failed.len(),
+            failed
+                .iter()
+                .take(5)
+                .map(|(id, err)| format!("{id}: {err}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    }
+}
+impl Actions {
+    fn new(target: ProcTarget, info: &ProcInfo) -> Self {
+        let sched = info
+            .pids_of(&target)
+            .first()
+            .and_then(|&pid| info.processes.iter().find(|p| p.pid == pid))
+            .map(|p| p.sched);
+        Self {
+            target,
+            all_threads: true,
+            nice: sched.map_or(0, |s| s.nice),
+            io_priority: sched.and_then(|s| s.io_priority).unwrap_or(IoPriority {
+                class: IoClass::None,
+                level: 4,
+            }),
+            policy: sched.map_or(SchedPolicy::Other, |s| s.policy),
+            rt_priority: sched.map_or(1, |s| s.rt_priority.max(1)),
+            affinity: sched
+                .and_then(|s| s.affinity)
+                .map_or_else(String::new, |h| info.strings.get(h).to_owned()),
+        }
     }
 }
 struct Table<'a, I: Iterator<Item = Row<'a>>> {
     id_header: &'a str,
+    columns: &'a [Column],
+    selected: Option<&'a ProcTarget>,
     sort_by: &'a mut ProcSortBy,
     rows: I,
 }
@@ -116,13 +1288,52 @@ struct Row<'a> {
     name: &'a str,
     hover_name: Option<&'a str>,
     stat: ProcStat,
+    cgroup_stat: Option<&'a CgroupStat>,
+    /// Shown before the name.
+    icon: Option<egui::TextureId>,
+    /// The owning uid, and its passwd entry if there is one.
+    user: Option<(u32, Option<&'a UserInfo>)>,
+    /// The pid within its container, if any.
+    ns_pid: Option<u32>,
+    /// The innermost systemd service or scope.
+    unit: Option<&'a str>,
+    task: Option<TaskInfo>,
+    /// Along with the formatted affinity.
+    sched: Option<(SchedInfo, &'a str)>,
+    tree: Option<TreeCell>,
+    /// `None` for rows that cannot be selected or signalled.
+    target: Option<ProcTarget>,
+}
+#[derive(Default)]
+struct TableResponse {
+    /// The row whose subtree was expanded or collapsed.
+    toggled: Option<ProcTarget>,
+    clicked: Option<ProcTarget>,
+    signal: Option<(ProcTarget, Signal)>,
+    actions: Option<ProcTarget>,
+    /// The row whose name was clicked.
+    opened: Option<ProcTarget>,
+}
+#[derive(Default)]
+struct RowResponse {
+    toggled: bool,
+    clicked: bool,
+    signal: Option<Signal>,
+    actions: bool,
+    opened: bool,
+}
+struct TreeCell {
+    depth: usize,
+    /// `None` if there are no children to collapse.
+    collapsed: Option<bool>,
 }
 impl<'a, I: Iterator<Item = Row<'a>>> Table<'a, I> {
-    fn render(mut self, ui: &mut Ui) {
+    fn render(mut self, ui: &mut Ui) -> TableResponse {
         let row_height = ui.text_style_height(&TextStyle::Body);
         let spacing = ui.style().spacing.item_spacing;
-        let total_col_spacing = 6.0 * spacing.x;
-        let col_width = (ui.available_width() - total_col_spacing) / 7.0;
+        let num_cols = 2 + self.columns.len();
+        let total_col_spacing = (num_cols - 1) as f32 * spacing.x;
+        let col_width = (ui.available_width() - total_col_spacing) / num_cols as f32;
         egui::Frame::none()
             .fill(ui.style().visuals.widgets.hovered.bg_fill)
             .show(ui, |ui| {
@@ -133,84 +1344,152 @@ impl<'a, I: Iterator<Item = Row<'a>>> Table<'a, I> {
                     .show(ui, |ui| self.header(ui));
             });
 
-        egui::ScrollArea::vertical().show_rows(ui, row_height, 7, |ui, row_range| {
-            crate::vim_like_scroll(
-                ui,
-                2.0 * row_height,
-                4.0 * row_height * (row_range.end - row_range.start) as f32,
-            );
-            egui::Grid::new("table-body")
-                .min_col_width(col_width)
-                .max_col_width(col_width)
-                .spacing(spacing)
-                .striped(true)
-                .start_row(row_range.start)
-                .show(ui, |ui| self.rows.for_each(|row| row.render(ui)));
-        });
+        egui::ScrollArea::vertical()
+            .show_rows(ui, row_height, 7, |ui, row_range| {
+                crate::vim_like_scroll(
+                    ui,
+                    2.0 * row_height,
+                    4.0 * row_height * (row_range.end - row_range.start) as f32,
+                );
+                egui::Grid::new("table-body")
+                    .min_col_width(col_width)
+                    .max_col_width(col_width)
+                    .spacing(spacing)
+                    .striped(true)
+                    .start_row(row_range.start)
+                    .show(ui, |ui| {
+                        let mut ret = TableResponse::default();
+                        for row in self.rows {
+                            let target = row.target.clone();
+                            let selected = target.is_some() && target.as_ref() == self.selected;
+                            let resp = row.render(ui, self.columns, selected);
+                            if resp.toggled {
+                                ret.toggled = target.clone();
+                            }
+                            if resp.clicked {
+                                ret.clicked = target.clone();
+                            }
+                            if resp.actions {
+                                ret.actions = target.clone();
+                            }
+                            if resp.opened {
+                                ret.opened = target.clone();
+                            }
+                            if let Some(signal) = resp.signal {
+                                ret.signal = target.map(|target| (target, signal));
+                            }
+                        }
+                        ret
+                    })
+                    .inner
+            })
+            .inner
     }
     fn header(&mut self, ui: &mut Ui) {
-        for (i, title) in [
-            &format!("{} (i)", self.id_header),
-            "Name (n)",
-            "User cpu% (c)",
-            "Sys cpu% (c)",
-            "Disk read (r)",
-            "Disk write (w)",
-            "Mem (m)",
+        for (title, sort_by) in [
+            (&*format!("{} (i)", self.id_header), ProcSortBy::Id),
+            ("Name (n)", ProcSortBy::Name),
         ]
-        .iter()
-        .enumerate()
-        {
-            ui.add_sized(ui.available_size(), |ui: &mut Ui| {
-                ui.selectable_value(
-                    self.sort_by,
-                    match i {
-                        0 => ProcSortBy::Id,
-                        1 => ProcSortBy::Name,
-                        2 | 3 => ProcSortBy::Cpu,
-                        4 => ProcSortBy::DiskRead,
-                        5 => ProcSortBy::DiskWrite,
-                        6 => ProcSortBy::Memory,
-                        _ => unreachable!(),
-                    },
-                    *title,
-                )
+        .into_iter()
+        .map(|(title, sort_by)| (title, Some(sort_by)))
+        .chain(
+            self.columns
+                .iter()
+                .map(|column| (column.title(), column.sort_by())),
+        ) {
+            ui.add_sized(ui.available_size(), |ui: &mut Ui| match sort_by {
+                Some(sort_by) => ui.selectable_value(self.sort_by, sort_by, title),
+                None => ui.label(title),
             });
         }
     }
 }
 impl<'a> Row<'a> {
-    fn render(self, ui: &mut Ui) {
-        ui.label(self.id);
-        let resp_name = ui.monospace(self.name);
+    fn render(self, ui: &mut Ui, columns: &[Column], selected: bool) -> RowResponse {
+        const INDENT: f32 = 8.0;
+        const TOGGLE_WIDTH: f32 = 14.0;
+        let mut ret = RowResponse::default();
+        let resp_id = ui.selectable_label(selected, &self.id);
+        ret.clicked = resp_id.clicked();
+        if self.target.is_some() {
+            resp_id.context_menu(|ui| {
+                ui.label("Send signal");
+                for signal in [
+                    Signal::SIGTERM,
+                    Signal::SIGKILL,
+                    Signal::SIGSTOP,
+                    Signal::SIGCONT,
+                    Signal::SIGHUP,
+                    Signal::SIGINT,
+                ] {
+                    if ui.button(signal.as_str()).clicked() {
+                        ret.signal = Some(signal);
+                        ui.close_menu();
+                    }
+                }
+                ui.menu_button("Other", |ui| {
+                    for signal in Signal::iterator() {
+                        if ui.button(signal.as_str()).clicked() {
+                            ret.signal = Some(signal);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.separator();
+                if ui.button("Priority and affinity (a)").clicked() {
+                    ret.actions = true;
+                    ui.close_menu();
+                }
+            });
+        }
+        let resp_name = match (&self.tree, self.icon) {
+            (None, None) => ui.monospace(self.name),
+            (None, Some(icon)) => {
+                ui.horizontal(|ui| {
+                    let size = ui.text_style_height(&TextStyle::Body);
+                    ui.image(egui::load::SizedTexture::new(icon, [size, size]));
+                    ui.monospace(self.name)
+                })
+                .inner
+            }
+            (Some(tree), _) => {
+                ui.horizontal(|ui| {
+                    ui.add_space(INDENT * tree.depth as f32);
+                    match tree.collapsed {
+                        Some(collapsed) => {
+                            let text = if collapsed { "+" } else { "-" };
+                            ret.toggled = ui
+                                .add_sized(
+                                    [TOGGLE_WIDTH, ui.available_height()],
+                                    egui::Button::new(text).small(),
+                                )
+                                .clicked();
+                        }
+                        None => ui.add_space(TOGGLE_WIDTH),
+                    }
+                    ui.monospace(self.name)
+                })
+                .inner
+            }
+        };
+        if matches!(self.target, Some(ProcTarget::Process(_))) {
+            ret.opened = resp_name
+                .interact(Sense::click())
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked();
+        }
         if let Some(hover_name) = self.hover_name {
             ui.interact(resp_name.rect, Id::new("name-hover"), Sense::hover())
                 .on_hover_ui_at_pointer(|ui| {
                     ui.monospace(hover_name);
                 });
         }
-        metric_cell(
-            ui,
-            self.stat.user_time_millis > 0,
-            millis_to_percent(self.stat.user_time_millis),
-        );
-        metric_cell(
-            ui,
-            self.stat.system_time_millis > 0,
-            millis_to_percent(self.stat.system_time_millis),
-        );
-        metric_cell(
-            ui,
-            self.stat.disk_read_bytes_per_second > 0,
-            Show::rate(self.stat.disk_read_bytes_per_second as f64, ""),
-        );
-        metric_cell(
-            ui,
-            self.stat.disk_write_bytes_per_second > 0,
-            Show::rate(self.stat.disk_write_bytes_per_second as f64, ""),
-        );
-        metric_cell(ui, false, Show::size(self.stat.mem_bytes as f64));
+        for column in columns {
+            let (highlight, text) = column.cell(&self);
+            metric_cell(ui, highlight, text);
+        }
         ui.end_row();
+        return ret;
 
         fn metric_cell(ui: &mut Ui, highlight: bool, text: String) {
             const HIGHLIGHT: Color32 = Color32::from_rgb(245, 196, 97);
@@ -225,10 +1504,318 @@ impl<'a> Row<'a> {
                 ui.add_sized(ui.available_size(), |ui: &mut Ui| ui.label(text));
             }
         }
-
-        fn millis_to_percent(v: u32) -> String {
-            assert!(v % 10 == 0);
-            format!("{}%", v / 10)
+    }
+}
+impl Column {
+    const ALL: [Self; 39] = [
+        Self::UserCpu,
+        Self::SysCpu,
+        Self::ChildrenCpu,
+        Self::DiskRead,
+        Self::DiskWrite,
+        Self::NetSend,
+        Self::NetReceive,
+        Self::Mem,
+        Self::Pss,
+        Self::Uss,
+        Self::RssAnon,
+        Self::RssFile,
+        Self::RssShmem,
+        Self::Swap,
+        Self::MinorFaults,
+        Self::MajorFaults,
+        Self::VoluntarySwitches,
+        Self::InvoluntarySwitches,
+        Self::RunDelay,
+        Self::Gpu,
+        Self::Vram,
+        Self::CgroupCpu,
+        Self::CgroupMem,
+        Self::CgroupRead,
+        Self::CgroupWrite,
+        Self::CgroupPids,
+        Self::Unit,
+        Self::User,
+        Self::Nice,
+        Self::Policy,
+        Self::IoPriority,
+        Self::Affinity,
+        Self::State,
+        Self::StartTime,
+        Self::Elapsed,
+        Self::Tty,
+        Self::Ppid,
+        Self::NsPid,
+        Self::Threads,
+    ];
+    const DEFAULT: [Self; 10] = [
+        Self::UserCpu,
+        Self::SysCpu,
+        Self::DiskRead,
+        Self::DiskWrite,
+        Self::Mem,
+        Self::CgroupCpu,
+        Self::CgroupMem,
+        Self::CgroupRead,
+        Self::CgroupPids,
+        Self::Nice,
+    ];
+    fn applies_to(self, nav: ProcessNavigation) -> bool {
+        match self {
+            Self::CgroupCpu
+            | Self::CgroupMem
+            | Self::CgroupRead
+            | Self::CgroupWrite
+            | Self::CgroupPids => nav == ProcessNavigation::Cgroups,
+            Self::Nice
+            | Self::Policy
+            | Self::IoPriority
+            | Self::Affinity
+            | Self::State
+            | Self::StartTime
+            | Self::Elapsed
+            | Self::Tty
+            | Self::Ppid
+            | Self::NsPid
+            | Self::Threads
+            | Self::Unit
+            | Self::User => matches!(
+                nav,
+                ProcessNavigation::Processes | ProcessNavigation::Threads
+            ),
+            _ => true,
         }
     }
+    fn title(self) -> &'static str {
+        match self {
+            Self::UserCpu => "User cpu% (c)",
+            Self::SysCpu => "Sys cpu% (c)",
+            Self::ChildrenCpu => "Children cpu%",
+            Self::DiskRead => "Disk read (r)",
+            Self::DiskWrite => "Disk write (w)",
+            Self::NetSend => "Net send",
+            Self::NetReceive => "Net recv",
+            Self::Mem => "Mem (m)",
+            Self::Pss => "PSS",
+            Self::Uss => "USS",
+            Self::RssAnon => "Anon",
+            Self::RssFile => "File",
+            Self::RssShmem => "Shmem",
+            Self::Swap => "Swap",
+            Self::MinorFaults => "Minflt/s",
+            Self::MajorFaults => "Majflt/s",
+            Self::VoluntarySwitches => "Vcsw/s",
+            Self::InvoluntarySwitches => "Ivcsw/s",
+            Self::RunDelay => "Delay%",
+            Self::Gpu => "GPU%",
+            Self::Vram => "VRAM",
+            Self::CgroupCpu => "cg cpu%",
+            Self::CgroupMem => "cg mem",
+            Self::CgroupRead => "cg read",
+            Self::CgroupWrite => "cg write",
+            Self::CgroupPids => "cg pids",
+            Self::Unit => "Unit",
+            Self::User => "User",
+            Self::Nice => "Nice",
+            Self::Policy => "Policy",
+            Self::IoPriority => "I/O prio",
+            Self::Affinity => "Affinity",
+            Self::State => "State",
+            Self::StartTime => "Started",
+            Self::Elapsed => "Elapsed",
+            Self::Tty => "TTY",
+            Self::Ppid => "Ppid",
+            Self::NsPid => "NSpid",
+            Self::Threads => "Threads",
+        }
+    }
+    fn sort_by(self) -> Option<ProcSortBy> {
+        Some(match self {
+            Self::UserCpu | Self::SysCpu => ProcSortBy::Cpu,
+            Self::ChildrenCpu => ProcSortBy::ChildrenCpu,
+            Self::DiskRead => ProcSortBy::DiskRead,
+            Self::DiskWrite => ProcSortBy::DiskWrite,
+            Self::NetSend => ProcSortBy::NetSend,
+            Self::NetReceive => ProcSortBy::NetReceive,
+            Self::Mem => ProcSortBy::Memory,
+            Self::Pss => ProcSortBy::Pss,
+            Self::Uss => ProcSortBy::Uss,
+            Self::RssAnon => ProcSortBy::RssAnon,
+            Self::RssFile => ProcSortBy::RssFile,
+            Self::RssShmem => ProcSortBy::RssShmem,
+            Self::Swap => ProcSortBy::Swap,
+            Self::MinorFaults => ProcSortBy::MinorFaults,
+            Self::MajorFaults => ProcSortBy::MajorFaults,
+            Self::VoluntarySwitches => ProcSortBy::VoluntarySwitches,
+            Self::InvoluntarySwitches => ProcSortBy::InvoluntarySwitches,
+            Self::RunDelay => ProcSortBy::RunDelay,
+            Self::Gpu => ProcSortBy::Gpu,
+            Self::Vram => ProcSortBy::Vram,
+            Self::Nice => ProcSortBy::Nice,
+            Self::State => ProcSortBy::State,
+            Self::StartTime => ProcSortBy::StartTime,
+            Self::Elapsed => ProcSortBy::Elapsed,
+            Self::Tty => ProcSortBy::Tty,
+            Self::Ppid => ProcSortBy::Ppid,
+            Self::Threads => ProcSortBy::Threads,
+            Self::CgroupCpu => ProcSortBy::CgroupCpu,
+            Self::CgroupMem => ProcSortBy::CgroupMemory,
+            Self::CgroupRead => ProcSortBy::CgroupRead,
+            Self::CgroupWrite => ProcSortBy::CgroupWrite,
+            Self::CgroupPids => ProcSortBy::CgroupPids,
+            Self::Unit
+            | Self::User
+            | Self::NsPid
+            | Self::Policy
+            | Self::IoPriority
+            | Self::Affinity => return None,
+        })
+    }
+    /// Returns whether to highlight the cell, and its text.
+    fn cell(self, row: &Row) -> (bool, String) {
+        let stat = &row.stat;
+        match self {
+            Self::UserCpu => (
+                stat.user_time_millis > 0,
+                millis_to_percent(stat.user_time_millis),
+            ),
+            Self::SysCpu => (
+                stat.system_time_millis > 0,
+                millis_to_percent(stat.system_time_millis),
+            ),
+            Self::ChildrenCpu => (
+                stat.children_time_millis > 0,
+                millis_to_percent(stat.children_time_millis),
+            ),
+            Self::DiskRead => (
+                stat.disk_read_bytes_per_second > 0,
+                Show::rate(stat.disk_read_bytes_per_second as f64, ""),
+            ),
+            Self::DiskWrite => (
+                stat.disk_write_bytes_per_second > 0,
+                Show::rate(stat.disk_write_bytes_per_second as f64, ""),
+            ),
+            Self::NetSend => (
+                stat.net_send_bytes_per_second > 0,
+                Show::rate(stat.net_send_bytes_per_second as f64, ""),
+            ),
+            Self::NetReceive => (
+                stat.net_receive_bytes_per_second > 0,
+                Show::rate(stat.net_receive_bytes_per_second as f64, ""),
+            ),
+            Self::Mem => (false, Show::size(stat.mem_bytes as f64)),
+            Self::Pss => (false, Show::size(stat.pss_bytes as f64)),
+            Self::Uss => (false, Show::size(stat.uss_bytes as f64)),
+            Self::RssAnon => (false, Show::size(stat.rss_anon_bytes as f64)),
+            Self::RssFile => (false, Show::size(stat.rss_file_bytes as f64)),
+            Self::RssShmem => (false, Show::size(stat.rss_shmem_bytes as f64)),
+            Self::Swap => (false, Show::size(stat.swap_bytes as f64)),
+            Self::MinorFaults => (
+                stat.minor_faults_per_second > 0,
+                stat.minor_faults_per_second.to_string(),
+            ),
+            Self::MajorFaults => (
+                stat.major_faults_per_second > 0,
+                stat.major_faults_per_second.to_string(),
+            ),
+            Self::VoluntarySwitches => (
+                stat.voluntary_switches_per_second > 0,
+                stat.voluntary_switches_per_second.to_string(),
+            ),
+            Self::InvoluntarySwitches => (
+                stat.involuntary_switches_per_second > 0,
+                stat.involuntary_switches_per_second.to_string(),
+            ),
+            Self::RunDelay => (
+                stat.run_delay_millis > 0,
+                format!("{}%", (stat.run_delay_millis + 5) / 10),
+            ),
+            Self::Gpu => (
+                stat.gpu_time_millis > 0,
+                format!("{}%", (stat.gpu_time_millis + 5) / 10),
+            ),
+            Self::Vram => (false, Show::size(stat.gpu_vram_bytes as f64)),
+            Self::CgroupCpu
+            | Self::CgroupMem
+            | Self::CgroupRead
+            | Self::CgroupWrite
+            | Self::CgroupPids => {
+                let Some(cg) = row.cgroup_stat else {
+                    return (false, "-".to_owned());
+                };
+                match self {
+                    Self::CgroupCpu => {
+                        let millis = cg.user_time_millis + cg.system_time_millis;
+                        (millis > 0, format!("{}%", (millis + 5) / 10))
+                    }
+                    Self::CgroupMem => (false, Show::size(cg.mem_bytes as f64)),
+                    Self::CgroupRead => (
+                        cg.io_read_bytes_per_second > 0,
+                        Show::rate(cg.io_read_bytes_per_second as f64, ""),
+                    ),
+                    Self::CgroupWrite => (
+                        cg.io_write_bytes_per_second > 0,
+                        Show::rate(cg.io_write_bytes_per_second as f64, ""),
+                    ),
+                    Self::CgroupPids => (false, cg.pids.to_string()),
+                    _ => unreachable!(),
+                }
+            }
+            Self::Unit => (false, row.unit.unwrap_or("-").to_owned()),
+            Self::NsPid => match row.ns_pid {
+                Some(ns_pid) => (false, ns_pid.to_string()),
+                None => (false, "-".to_owned()),
+            },
+            Self::User => match row.user {
+                Some((_, Some(user))) => (false, format!("{} ({})", user.name, user.kind)),
+                Some((uid, None)) => (false, uid.to_string()),
+                None => (false, "-".to_owned()),
+            },
+            Self::Nice | Self::Policy | Self::IoPriority | Self::Affinity => {
+                let Some((sched, affinity)) = row.sched else {
+                    return (false, "-".to_owned());
+                };
+                match self {
+                    Self::Nice => (sched.nice != 0, sched.nice.to_string()),
+                    Self::Policy => (
+                        sched.policy != SchedPolicy::Other,
+                        match sched.policy.is_realtime() {
+                            true => format!("{}/{}", sched.policy, sched.rt_priority),
+                            false => sched.policy.to_string(),
+                        },
+                    ),
+                    Self::IoPriority => match sched.io_priority {
+                        Some(io) => (io.class != IoClass::None, io.to_string()),
+                        None => (false, "?".to_owned()),
+                    },
+                    Self::Affinity => (false, affinity.to_owned()),
+                    _ => unreachable!(),
+                }
+            }
+            Self::State
+            | Self::StartTime
+            | Self::Elapsed
+            | Self::Tty
+            | Self::Ppid
+            | Self::Threads => {
+                let Some(task) = row.task else {
+                    return (false, "-".to_owned());
+                };
+                match self {
+                    Self::State => (matches!(task.state, 'R' | 'D'), task.state.to_string()),
+                    Self::StartTime => (false, task.format_start_time()),
+                    Self::Elapsed => (false, task.format_elapsed()),
+                    Self::Tty => (false, task.tty_name().unwrap_or_else(|| "-".to_owned())),
+                    Self::Ppid => (false, task.ppid.to_string()),
+                    Self::Threads => (task.num_threads > 1, task.num_threads.to_string()),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+fn millis_to_percent(v: u32) -> String {
+    assert!(v.is_multiple_of(10));
+    format!("{}%", v / 10)
 }
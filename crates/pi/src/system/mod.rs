@@ -9,7 +9,7 @@ use eframe::egui::{
 };
 use sysinfo::{Series, SysInfo};
 
-mod time_series;
+pub mod time_series;
 
 const TICK_PER_SEC: f64 = util::SUBSEC as f64;
 const MARGIN_PIXELS: f32 = 6.0;
@@ -24,7 +24,9 @@ pub enum ValueKind {
     Temperature,
 }
 impl<'a> TimeSeries<'a> {
-    pub fn render(&self, ui: &mut Ui, series: &[(&str, &Series<f64>)]) {
+    /// Every series covers the same time span, sampled more or less often depending on `N`.
+    pub fn render<const N: usize>(&self, ui: &mut Ui, series: &[(&str, &Series<f64, N>)]) {
+        let seconds_per_sample = (HISTORY - 1) as f64 * TICK_DELAY.as_secs_f64() / (N - 1) as f64;
         let series_max_y = series
             .iter()
             .map(|(_, series)| series.iter().max_by(f64::total_cmp).unwrap())
@@ -65,7 +67,7 @@ impl<'a> TimeSeries<'a> {
             .allow_zoom(false)
             .allow_scroll(false)
             .allow_drag(false)
-            .include_x(-((HISTORY - 1) as f64) * TICK_DELAY.as_secs_f64())
+            .include_x(-((N - 1) as f64) * seconds_per_sample)
             .include_x(0)
             .include_y(0)
             .include_y(self.max_y.unwrap_or(1.2 * series_max_y))
@@ -101,20 +103,19 @@ impl<'a> TimeSeries<'a> {
                 }
                 for (name, series) in series {
                     let chunk_size =
-                        (Series::<f64>::capacity() as f32 / plot_width_pixels) as usize;
+                        ((Series::<f64, N>::capacity() as f32 / plot_width_pixels) as usize).max(1);
                     let (first, middle, last) = series.chunks(chunk_size);
                     let mut points = Vec::new();
                     let max = |slice: &'_ [f64]| {
                         slice.iter().copied().max_by(f64::total_cmp).unwrap_or(0.0)
                     };
                     if !first.is_empty() {
-                        points.push([-(HISTORY as f64 * TICK_DELAY.as_secs_f64()), max(first)]);
+                        points.push([-(N as f64 * seconds_per_sample), max(first)]);
                     }
                     points.extend(middle.enumerate().map(|(i, m)| {
                         [
-                            -((HISTORY - first.len() - chunk_size * (i + 1)) as f64)
-                                * (TICK_DELAY.as_secs_f64() * HISTORY as f64
-                                    / (HISTORY - chunk_size) as f64),
+                            -((N - first.len() - chunk_size * (i + 1)) as f64)
+                                * (seconds_per_sample * N as f64 / (N - chunk_size) as f64),
                             max(m),
                         ]
                     }));
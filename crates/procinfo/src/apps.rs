@@ -0,0 +1,248 @@
+use crate::cgroupfs;
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Executables that launch other programs, so that a `.desktop` file running them says
+/// nothing about which application a process is.
+const LAUNCHERS: [&str; 14] = [
+    "env",
+    "sh",
+    "bash",
+    "python",
+    "python3",
+    "perl",
+    "java",
+    "flatpak",
+    "snap",
+    "gio",
+    "gtk-launch",
+    "xdg-open",
+    "sudo",
+    "pkexec",
+];
+/// Icon sizes to look for in the `hicolor` theme, in order of preference.
+const ICON_SIZES: [&str; 6] = ["48x48", "64x64", "32x32", "128x128", "256x256", "24x24"];
+
+/// From the `[Desktop Entry]` group of a `.desktop` file.
+#[derive(Debug)]
+pub(crate) struct DesktopEntry {
+    /// The unlocalized `Name`.
+    pub name: String,
+    /// A PNG file for the `Icon`, if one is found. SVG icons are skipped.
+    pub icon: Option<PathBuf>,
+    /// The file name of the program run by `Exec`, unless it is a launcher.
+    executable: Option<String>,
+    /// Set by `NoDisplay`, as for helpers that are not meant to be launched by users.
+    no_display: bool,
+}
+
+/// The `.desktop` files in `applications` under `$XDG_DATA_HOME` and each of
+/// `$XDG_DATA_DIRS`, by desktop file id, such as `org.gnome.Terminal`.
+#[derive(Debug, Default)]
+pub(crate) struct DesktopEntries {
+    by_id: HashMap<String, DesktopEntry>,
+    /// Desktop file ids by the file name of their executable.
+    by_executable: HashMap<String, String>,
+}
+impl DesktopEntries {
+    /// Reads every `.desktop` file, of which there are usually a few hundred.
+    pub(crate) fn load() -> Self {
+        let data_dirs = data_dirs();
+        let mut ret = Self::default();
+        // Earlier directories take precedence
+        for data_dir in &data_dirs {
+            let root = data_dir.join("applications");
+            let mut dirs = vec![root.clone()];
+            while let Some(dir) = dirs.pop() {
+                let Ok(entries) = fs::read_dir(&dir) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    // Without following links, which could lead out of the directory or in circles
+                    if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                        dirs.push(path);
+                        continue;
+                    }
+                    if path.extension() != Some(OsStr::new("desktop")) {
+                        continue;
+                    }
+                    // Files in subdirectories are named like `kde4-kate.desktop`
+                    let relative = path.strip_prefix(&root).unwrap().with_extension("");
+                    let id = relative.to_string_lossy().replace('/', "-");
+                    if ret.by_id.contains_key(&id) {
+                        continue;
+                    }
+                    let Some(entry) = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|data| DesktopEntry::parse(&data, &data_dirs))
+                    else {
+                        continue;
+                    };
+                    if let Some(executable) =
+                        entry.executable.as_ref().filter(|_| !entry.no_display)
+                    {
+                        ret.by_executable
+                            .entry(executable.clone())
+                            .or_insert_with(|| id.clone());
+                    }
+                    ret.by_id.insert(id, entry);
+                }
+            }
+        }
+        ret
+    }
+    pub(crate) fn get(&self, app_id: &str) -> Option<&DesktopEntry> {
+        self.by_id.get(app_id)
+    }
+    /// Follows the names that systemd recommends desktop environments give the units they start
+    /// applications in, such as `app-gnome-firefox-1234.scope` or
+    /// `app-flatpak-org.gimp.GIMP-5678.scope`, as well as those of snaps, such as
+    /// `snap.firefox.firefox-{uuid}.scope`. Looks from the innermost unit outwards.
+    pub(crate) fn app_id_of_cgroup(&self, path: &str) -> Option<String> {
+        cgroupfs::unit_paths(path).find_map(|unit| {
+            let name = unit.rsplit('/').next().unwrap();
+            match name.strip_suffix(".slice") {
+                // Slices like `app-org.gnome.Terminal.slice` hold the scopes of terminal
+                // tabs, but others like `app-graphical.slice` are not applications
+                Some(slice) => slice
+                    .strip_prefix("app-")
+                    .map(cgroupfs::unescape_unit_name)
+                    .filter(|id| self.by_id.contains_key(id)),
+                None => app_id_of_unit(name),
+            }
+        })
+    }
+    /// The application whose `.desktop` file runs `cmdline`, by the file name of its program.
+    pub(crate) fn app_id_of_cmdline(&self, cmdline: &str) -> Option<&str> {
+        let program = cmdline.split_ascii_whitespace().next()?;
+        let executable = program.rsplit('/').next().unwrap();
+        self.by_executable.get(executable).map(String::as_str)
+    }
+}
+impl DesktopEntry {
+    /// Returns `None` unless of `Type=Application`, or if `Hidden`.
+    fn parse(data: &str, data_dirs: &[PathBuf]) -> Option<Self> {
+        let mut in_entry = false;
+        let mut kind = None;
+        let mut name = None;
+        let mut icon = None;
+        let mut exec = None;
+        let mut no_display = false;
+        let mut hidden = false;
+        for line in data.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                if in_entry {
+                    break;
+                }
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').filter(|_| in_entry) else {
+                continue;
+            };
+            // Localized keys like `Name[sv]` are skipped
+            let value = value.trim();
+            match key.trim() {
+                "Type" => kind = Some(value),
+                "Name" => name = Some(value),
+                "Icon" => icon = Some(value),
+                "Exec" => exec = Some(value),
+                "NoDisplay" => no_display = value == "true",
+                "Hidden" => hidden = value == "true",
+                _ => {}
+            }
+        }
+        if kind != Some("Application") || hidden {
+            return None;
+        }
+        let executable = exec
+            .and_then(|exec| exec.split_ascii_whitespace().next())
+            .map(|program| program.trim_matches('"').rsplit('/').next().unwrap())
+            .filter(|executable| !LAUNCHERS.contains(executable))
+            .map(str::to_owned);
+        Some(Self {
+            name: name?.to_owned(),
+            icon: icon.and_then(|icon| find_icon(icon, data_dirs)),
+            executable,
+            no_display,
+        })
+    }
+}
+
+/// Parses unit names like `app[-{launcher}]-{app id}-{random}.scope` and
+/// `app[-{launcher}]-{app id}[@{random}].service`, where dashes within fields are escaped.
+fn app_id_of_unit(name: &str) -> Option<String> {
+    if let Some(snap) = name.strip_prefix("snap.") {
+        let snap = match snap.strip_suffix(".scope") {
+            // Followed by a dash and a UUID
+            Some(scope) => scope.get(..scope.len().checked_sub(37)?)?,
+            None => snap.strip_suffix(".service")?,
+        };
+        // Named like their `.desktop` files, such as `firefox_firefox`
+        let (snap, app) = snap.split_once('.')?;
+        return Some(format!("{snap}_{app}"));
+    }
+    let fields = name.strip_prefix("app-")?;
+    let fields = match fields.strip_suffix(".scope") {
+        Some(scope) => scope.rsplit_once('-')?.0,
+        None => {
+            let service = fields.strip_suffix(".service")?;
+            service
+                .split_once('@')
+                .map_or(service, |(fields, _)| fields)
+        }
+    };
+    // Optionally preceded by the launcher, such as `gnome` or `flatpak`
+    let app_id = fields.rsplit('-').next().unwrap();
+    Some(cgroupfs::unescape_unit_name(app_id))
+}
+
+fn data_dirs() -> Vec<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")),
+    };
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_owned());
+    data_home
+        .into_iter()
+        .chain(
+            data_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        )
+        .collect()
+}
+
+/// Looks only in the `hicolor` theme, which every theme falls back to and applications install
+/// their icons into, and then in `/usr/share/pixmaps`.
+fn find_icon(icon: &str, data_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if icon.starts_with('/') {
+        let path = PathBuf::from(icon);
+        return (path.extension().is_some_and(|ext| ext == "png") && path.is_file())
+            .then_some(path);
+    }
+    let file_name = &format!("{icon}.png");
+    data_dirs
+        .iter()
+        .flat_map(|dir| {
+            ICON_SIZES.iter().map(move |size| {
+                dir.join("icons/hicolor")
+                    .join(size)
+                    .join("apps")
+                    .join(file_name)
+            })
+        })
+        .chain([Path::new("/usr/share/pixmaps").join(file_name)])
+        .find(|path| path.is_file())
+}
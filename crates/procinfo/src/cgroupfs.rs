@@ -0,0 +1,216 @@
+use crate::procfs::{check_io_err, read_file_to_end, read_file_to_string};
+use std::{
+    fs::File,
+    iter,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+/// Returns where the cgroup v2 hierarchy is mounted, if anywhere.
+///
+/// Pure v2 systems mount it at `/sys/fs/cgroup`, hybrid systems at `/sys/fs/cgroup/unified`.
+pub fn find_cgroup2_root() -> Option<PathBuf> {
+    ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"]
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|root| root.join("cgroup.controllers").exists())
+}
+
+/// Returns the parent of a cgroup path such as `/user.slice/user-1000.slice`.
+pub fn parent_path(path: &str) -> Option<&str> {
+    match path.rsplit_once('/')? {
+        ("", "") => None,
+        ("", _) => Some("/"),
+        (parent, _) => Some(parent),
+    }
+}
+
+/// Whether a cgroup is named after a systemd unit, as are those that systemd manages, such as
+/// `/system.slice/sshd.service` and `/system.slice`.
+pub fn is_unit(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap();
+    [".slice", ".service", ".scope"]
+        .into_iter()
+        .any(|suffix| name.len() > suffix.len() && name.ends_with(suffix))
+}
+
+/// The units containing a cgroup, innermost first. Cgroups below a unit, such as those it
+/// delegates to a container, count toward it.
+pub fn unit_paths(path: &str) -> impl Iterator<Item = &str> {
+    iter::successors(Some(path), |path| parent_path(path)).filter(|path| is_unit(path))
+}
+
+/// The unit name of a unit cgroup path, such as `user@1000.service`.
+pub fn unit_name(path: &str) -> String {
+    unescape_unit_name(path.rsplit('/').next().unwrap())
+}
+
+/// Undoes the escaping of systemd unit names, such as `machine-my\x2dbox.scope`.
+pub fn unescape_unit_name(name: &str) -> String {
+    let mut ret = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .strip_prefix(b"x")
+            .and_then(|hex| hex.get(..2))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (byte, escaped) {
+            (b'\\', Some(unescaped)) => {
+                ret.push(unescaped);
+                rest = &tail[3..];
+            }
+            _ => {
+                ret.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&ret).into_owned()
+}
+
+/// The kernel's own accounting of a cgroup. Files missing for the root cgroup, or for
+/// controllers that are not enabled, are skipped.
+pub struct CgroupFiles {
+    /// The inode number of the cgroup directory, which is also its cgroup id.
+    pub id: u64,
+    /// `{cgroup}/cpu.stat`
+    cpu_stat: Option<File>,
+    /// `{cgroup}/memory.current`
+    memory_current: Option<File>,
+    /// `{cgroup}/io.stat`
+    io_stat: Option<File>,
+    /// `{cgroup}/pids.current`
+    pids_current: Option<File>,
+}
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CgroupCounters {
+    pub cumulative_user_usec: u64,
+    pub cumulative_system_usec: u64,
+    pub cumulative_read_bytes: u64,
+    pub cumulative_write_bytes: u64,
+    pub memory_bytes: u64,
+    pub pids: u64,
+}
+impl CgroupFiles {
+    pub fn new(root: &Path, path: &str) -> Option<Self> {
+        let dir = root.join(path.trim_start_matches('/'));
+        let id = match dir.metadata() {
+            Ok(metadata) => metadata.ino(),
+            Err(err) => {
+                check_io_err(err);
+                return None;
+            }
+        };
+        let open = |name: &str| File::open(dir.join(name)).map_err(check_io_err).ok();
+        Some(Self {
+            id,
+            cpu_stat: open("cpu.stat"),
+            memory_current: open("memory.current"),
+            io_stat: open("io.stat"),
+            pids_current: open("pids.current"),
+        })
+    }
+    pub fn get_counters(&mut self) -> CgroupCounters {
+        let mut ret = CgroupCounters::default();
+        let buf = &mut [0u8; 4096];
+        if let Some(data) = self
+            .cpu_stat
+            .as_mut()
+            .and_then(|file| read_file_to_string(file, buf))
+        {
+            for line in data.lines() {
+                match line.split_once(' ') {
+                    Some(("user_usec", value)) => ret.cumulative_user_usec = value.parse().unwrap(),
+                    Some(("system_usec", value)) => {
+                        ret.cumulative_system_usec = value.parse().unwrap()
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // One line per device, so longer than a page with dozens of loop or dm devices
+        let io_buf = &mut Vec::new();
+        if let Some(data) = self
+            .io_stat
+            .as_mut()
+            .and_then(|file| read_file_to_end(file, io_buf))
+        {
+            // e.g. `259:0 rbytes=1 wbytes=2 rios=3 wios=4 dbytes=0 dios=0`
+            for entry in data.lines().flat_map(|line| line.split(' ').skip(1)) {
+                match entry
+                    .split_once('=')
+                    .map(|(key, value)| (key, value.parse::<u64>()))
+                {
+                    Some(("rbytes", Ok(value))) => ret.cumulative_read_bytes += value,
+                    Some(("wbytes", Ok(value))) => ret.cumulative_write_bytes += value,
+                    _ => {}
+                }
+            }
+        }
+        let mut parse_single = |file: &mut Option<File>| {
+            file.as_mut()
+                .and_then(|file| read_file_to_string(file, buf))
+                .map_or(0, |data| data.trim().parse().unwrap())
+        };
+        ret.memory_bytes = parse_single(&mut self.memory_current);
+        ret.pids = parse_single(&mut self.pids_current);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units() {
+        assert!(is_unit("/system.slice"));
+        assert!(is_unit("/system.slice/sshd.service"));
+        assert!(is_unit("/init.scope"));
+        assert!(!is_unit("/"));
+        assert!(!is_unit("/.slice"));
+        assert!(!is_unit("/machine.slice/libpod-1234.scope/container"));
+    }
+
+    #[test]
+    fn unit_paths_innermost_first() {
+        let paths: Vec<&str> =
+            unit_paths("/user.slice/user-1000.slice/user@1000.service/app.slice/app-x.scope")
+                .collect();
+        assert_eq!(
+            paths,
+            [
+                "/user.slice/user-1000.slice/user@1000.service/app.slice/app-x.scope",
+                "/user.slice/user-1000.slice/user@1000.service/app.slice",
+                "/user.slice/user-1000.slice/user@1000.service",
+                "/user.slice/user-1000.slice",
+                "/user.slice",
+            ]
+        );
+        // Delegated cgroups count toward the unit delegating them
+        let paths: Vec<&str> = unit_paths("/machine.slice/libpod-1234.scope/container").collect();
+        assert_eq!(
+            paths,
+            ["/machine.slice/libpod-1234.scope", "/machine.slice"]
+        );
+        assert_eq!(unit_paths("/").count(), 0);
+    }
+
+    #[test]
+    fn unit_names() {
+        assert_eq!(
+            unit_name("/user.slice/user-1000.slice/user@1000.service"),
+            "user@1000.service"
+        );
+        assert_eq!(
+            unit_name(r"/machine.slice/machine-my\x2dbox.scope"),
+            "machine-my-box.scope"
+        );
+        assert_eq!(
+            unescape_unit_name(r"app-flatpak-org.gimp.GIMP\x2d2-5678.scope"),
+            "app-flatpak-org.gimp.GIMP-2-5678.scope"
+        );
+        // Incomplete or invalid escapes are kept as they are
+        assert_eq!(unescape_unit_name(r"a\xzz-b\x2"), r"a\xzz-b\x2");
+    }
+}
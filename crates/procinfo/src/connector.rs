@@ -0,0 +1,109 @@
+use crate::netlink::{self, u32_at};
+use std::{
+    io, mem,
+    os::fd::{AsRawFd, OwnedFd},
+};
+
+// From `linux/connector.h` and `linux/cn_proc.h`
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_EVENT_FORK: u32 = 0x1;
+const PROC_EVENT_EXEC: u32 = 0x2;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+const CN_MSG_LEN: usize = 20;
+/// The offset of `event_data` within `struct proc_event`.
+const EVENT_DATA_OFFSET: usize = 16;
+
+/// Events about whole processes. Those about individual threads are dropped.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ProcEvent {
+    Fork { pid: u32 },
+    Exec { pid: u32 },
+    Exit { pid: u32 },
+}
+
+/// A netlink socket subscribed to the kernel proc connector.
+pub(crate) struct ProcConnector {
+    fd: OwnedFd,
+}
+
+impl ProcConnector {
+    /// Requires `CAP_NET_ADMIN`, and being in the initial user and pid namespaces.
+    pub(crate) fn open() -> io::Result<Self> {
+        let fd = netlink::open(libc::NETLINK_CONNECTOR)?;
+        netlink::bind(&fd, CN_IDX_PROC)?;
+        // Fork storms overflow the default buffer. Failing this only loses more events.
+        let rcvbuf: libc::c_int = 4 << 20;
+        unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVBUFFORCE,
+                &rcvbuf as *const libc::c_int as *const libc::c_void,
+                mem::size_of_val(&rcvbuf) as libc::socklen_t,
+            )
+        };
+
+        // A `struct cn_msg` carrying the operation
+        let mut msg = Vec::with_capacity(CN_MSG_LEN + 4);
+        msg.extend(CN_IDX_PROC.to_ne_bytes());
+        msg.extend(CN_VAL_PROC.to_ne_bytes());
+        msg.extend(0u32.to_ne_bytes());
+        msg.extend(0u32.to_ne_bytes());
+        msg.extend(4u16.to_ne_bytes());
+        msg.extend(0u16.to_ne_bytes());
+        msg.extend(PROC_CN_MCAST_LISTEN.to_ne_bytes());
+        netlink::send(&fd, libc::NLMSG_DONE as u16, 0, &msg)?;
+        Ok(Self { fd })
+    }
+
+    /// Blocks until some events arrive. If we fall behind, the kernel drops events.
+    pub(crate) fn recv(&mut self) -> Vec<ProcEvent> {
+        let mut buf = [0u8; 8192];
+        let messages = match netlink::recv(&self.fd, &mut buf) {
+            Ok(messages) => messages,
+            Err(err) => {
+                match err.raw_os_error() {
+                    Some(libc::ENOBUFS) => {
+                        tracing::warn!("proc connector overflowed, dropping events")
+                    }
+                    Some(libc::EINTR) => {}
+                    _ => panic!("{err}"),
+                }
+                return Vec::new();
+            }
+        };
+
+        let mut ret = Vec::new();
+        for (_, msg) in messages {
+            if u32_at(msg, 0) != Some(CN_IDX_PROC) || u32_at(msg, 4) != Some(CN_VAL_PROC) {
+                continue;
+            }
+            let Some(event) = msg.get(CN_MSG_LEN..) else {
+                continue;
+            };
+            let data = |i: usize| u32_at(event, EVENT_DATA_OFFSET + 4 * i);
+            let event = match u32_at(event, 0) {
+                // parent_pid, parent_tgid, child_pid, child_tgid
+                Some(PROC_EVENT_FORK) => match (data(2), data(3)) {
+                    (Some(pid), Some(tgid)) if pid == tgid => ProcEvent::Fork { pid },
+                    _ => continue,
+                },
+                // process_pid, process_tgid
+                Some(PROC_EVENT_EXEC) => match data(1) {
+                    Some(pid) => ProcEvent::Exec { pid },
+                    None => continue,
+                },
+                Some(PROC_EVENT_EXIT) => match (data(0), data(1)) {
+                    (Some(pid), Some(tgid)) if pid == tgid => ProcEvent::Exit { pid },
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            ret.push(event);
+        }
+        ret
+    }
+}
@@ -0,0 +1,91 @@
+use crate::cgroupfs;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ContainerRuntime {
+    Docker,
+    Podman,
+    Containerd,
+    Nspawn,
+    Lxc,
+    /// Recognized only by its processes being in a pid namespace other than ours, as for
+    /// sandboxes like `bwrap`.
+    PidNamespace,
+}
+impl fmt::Display for ContainerRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Docker => "docker",
+            Self::Podman => "podman",
+            Self::Containerd => "containerd",
+            Self::Nspawn => "nspawn",
+            Self::Lxc => "lxc",
+            Self::PidNamespace => "pid namespace",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ContainerId {
+    pub runtime: ContainerRuntime,
+    /// The container id or machine name, or the pid namespace inode for
+    /// `ContainerRuntime::PidNamespace`.
+    pub id: String,
+}
+impl ContainerId {
+    /// Recognizes the cgroups that runtimes place containers in, such as
+    /// `/system.slice/docker-{id}.scope` with the systemd cgroup driver, or `/docker/{id}`
+    /// without it. The outermost container wins, so nested containers count as their host.
+    pub(crate) fn from_cgroup(path: &str) -> Option<Self> {
+        let scope = |component: &str, prefix: &str| -> Option<String> {
+            let id = component.strip_prefix(prefix)?.strip_suffix(".scope")?;
+            Some(id.to_owned())
+        };
+        let mut components = path.split('/').filter(|component| !component.is_empty());
+        while let Some(component) = components.next() {
+            let (runtime, id) = if let Some(id) = scope(component, "docker-") {
+                (ContainerRuntime::Docker, id)
+            } else if let Some(id) = scope(component, "libpod-") {
+                // Each container has a `libpod-conmon-{id}.scope` for its monitor process
+                if id.starts_with("conmon-") {
+                    continue;
+                }
+                (ContainerRuntime::Podman, id)
+            } else if let Some(id) = scope(component, "cri-containerd-") {
+                (ContainerRuntime::Containerd, id)
+            } else if let Some(name) = component
+                .strip_prefix("systemd-nspawn@")
+                .and_then(|name| name.strip_suffix(".service"))
+            {
+                (ContainerRuntime::Nspawn, cgroupfs::unescape_unit_name(name))
+            } else if let Some(name) = scope(component, "machine-") {
+                // Also used for virtual machines by libvirt, which are not containers
+                let name = cgroupfs::unescape_unit_name(&name);
+                if name.starts_with("qemu-") {
+                    continue;
+                }
+                (ContainerRuntime::Nspawn, name)
+            } else if let Some(name) = component.strip_prefix("lxc.payload.") {
+                (ContainerRuntime::Lxc, name.to_owned())
+            } else if matches!(component, "docker" | "lxc" | "lxc.payload") {
+                let id = components.next()?;
+                let runtime = match component {
+                    "docker" => ContainerRuntime::Docker,
+                    _ => ContainerRuntime::Lxc,
+                };
+                (runtime, id.to_owned())
+            } else {
+                continue;
+            };
+            return Some(Self { runtime, id });
+        }
+        None
+    }
+    /// Like `docker ps`, shortens hexadecimal ids to 12 digits.
+    pub fn short_id(&self) -> &str {
+        match self.id.len() == 64 && self.id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            true => &self.id[..12],
+            false => &self.id,
+        }
+    }
+}
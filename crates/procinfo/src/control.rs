@@ -0,0 +1,299 @@
+use crate::procfs;
+use nix::{
+    errno::Errno,
+    sched::{self, CpuSet},
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
+use std::{
+    fmt,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    ptr,
+};
+
+/// Sends `signal` to every process, given by its pid and start time since boot, returning the
+/// pids that failed along with why.
+///
+/// Typical failures are `EPERM` for processes of other users and `ESRCH` for processes that
+/// exited since the last update, even if their pid was since reused.
+pub fn send_signal(processes: &[(u32, u64)], signal: Signal) -> Vec<(u32, Errno)> {
+    processes
+        .iter()
+        .filter_map(|&(pid, start_time_ms)| {
+            let err = signal_process(pid, start_time_ms, signal).err()?;
+            Some((pid, err))
+        })
+        .collect()
+}
+/// A pidfd keeps referring to the process it was opened for, so once the start time read after
+/// opening it matches, it can't be another process that is signalled.
+fn signal_process(pid: u32, start_time_ms: u64, signal: Signal) -> Result<(), Errno> {
+    let ret = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    let pidfd = match Errno::result(ret) {
+        Ok(fd) => Some(unsafe { OwnedFd::from_raw_fd(fd as i32) }),
+        // Before Linux 5.3, which leaves a short window for the pid to be reused
+        Err(Errno::ENOSYS) => None,
+        Err(err) => return Err(err),
+    };
+    let started = procfs::TidStat::new_process(pid).and_then(|mut stat| stat.get_fields());
+    if started.map(|stat| stat.start_time_ms) != Some(start_time_ms) {
+        return Err(Errno::ESRCH);
+    }
+    match pidfd {
+        Some(pidfd) => {
+            let ret = unsafe {
+                libc::syscall(
+                    libc::SYS_pidfd_send_signal,
+                    pidfd.as_raw_fd(),
+                    signal as libc::c_int,
+                    ptr::null::<libc::siginfo_t>(),
+                    0,
+                )
+            };
+            Errno::result(ret).map(drop)
+        }
+        None => signal::kill(Pid::from_raw(pid as i32), signal),
+    }
+}
+
+/// Sets the nice value of every thread. Lowering it requires `CAP_SYS_NICE`.
+pub fn set_nice(tids: &[u32], nice: i8) -> Vec<(u32, Errno)> {
+    for_each(tids, |tid| {
+        // On Linux, `PRIO_PROCESS` refers to a single thread
+        let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice.into()) };
+        Errno::result(ret).map(drop)
+    })
+}
+
+pub fn set_io_priority(tids: &[u32], io_priority: IoPriority) -> Vec<(u32, Errno)> {
+    for_each(tids, |tid| {
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                tid as libc::c_int,
+                io_priority.to_raw() as libc::c_int,
+            )
+        };
+        Errno::result(ret).map(drop)
+    })
+}
+
+/// `rt_priority` must be within 1 to 99 for the realtime policies and zero otherwise.
+pub fn set_sched_policy(tids: &[u32], policy: SchedPolicy, rt_priority: u8) -> Vec<(u32, Errno)> {
+    let param = libc::sched_param {
+        sched_priority: rt_priority.into(),
+    };
+    for_each(tids, |tid| {
+        let ret =
+            unsafe { libc::sched_setscheduler(tid as libc::pid_t, policy.to_raw() as i32, &param) };
+        Errno::result(ret).map(drop)
+    })
+}
+
+pub fn set_affinity(tids: &[u32], cpus: &CpuSet) -> Vec<(u32, Errno)> {
+    for_each(tids, |tid| {
+        sched::sched_setaffinity(Pid::from_raw(tid as i32), cpus)
+    })
+}
+
+pub fn get_io_priority(tid: u32) -> Option<IoPriority> {
+    let ret =
+        unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, tid as libc::c_int) };
+    Errno::result(ret)
+        .ok()
+        .map(|raw| IoPriority::from_raw(raw as u32))
+}
+
+pub fn get_affinity(tid: u32) -> Option<CpuSet> {
+    sched::sched_getaffinity(Pid::from_raw(tid as i32)).ok()
+}
+
+fn for_each(ids: &[u32], f: impl Fn(u32) -> Result<(), Errno>) -> Vec<(u32, Errno)> {
+    ids.iter()
+        .filter_map(|&id| f(id).err().map(|err| (id, err)))
+        .collect()
+}
+
+/// Formats as a list of ranges, like `0-3,8`.
+pub fn format_cpu_list(cpus: &CpuSet) -> String {
+    let mut ret = String::new();
+    let mut cpu = 0;
+    while cpu < CpuSet::count() {
+        if !cpus.is_set(cpu).unwrap() {
+            cpu += 1;
+            continue;
+        }
+        let start = cpu;
+        while cpu + 1 < CpuSet::count() && cpus.is_set(cpu + 1).unwrap() {
+            cpu += 1;
+        }
+        if !ret.is_empty() {
+            ret.push(',');
+        }
+        match start == cpu {
+            true => ret.push_str(&start.to_string()),
+            false => ret.push_str(&format!("{start}-{cpu}")),
+        }
+        cpu += 1;
+    }
+    ret
+}
+
+/// Parses a list formatted like by `format_cpu_list`. Returns `None` if malformed or empty.
+pub fn parse_cpu_list(s: &str) -> Option<CpuSet> {
+    let mut ret = CpuSet::new();
+    let mut any = false;
+    for range in s
+        .split(',')
+        .map(str::trim)
+        .filter(|range| !range.is_empty())
+    {
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+            None => {
+                let cpu = range.parse().ok()?;
+                (cpu, cpu)
+            }
+        };
+        for cpu in start..=end {
+            ret.set(cpu).ok()?;
+            any = true;
+        }
+    }
+    any.then_some(ret)
+}
+
+const SCHED_DEADLINE: libc::c_int = 6;
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+const IOPRIO_CLASS_SHIFT: u32 = 13;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedPolicy {
+    Other,
+    Fifo,
+    RoundRobin,
+    Batch,
+    Idle,
+    Deadline,
+    /// Such as `SCHED_EXT`, or one added after this was written.
+    Unknown(u32),
+}
+impl SchedPolicy {
+    /// Every policy but `Deadline`, which needs parameters `sched_setscheduler` cannot pass.
+    pub const SETTABLE: [Self; 5] = [
+        Self::Other,
+        Self::Batch,
+        Self::Idle,
+        Self::Fifo,
+        Self::RoundRobin,
+    ];
+    pub fn from_raw(raw: u32) -> Self {
+        match raw as i32 {
+            libc::SCHED_OTHER => Self::Other,
+            libc::SCHED_FIFO => Self::Fifo,
+            libc::SCHED_RR => Self::RoundRobin,
+            libc::SCHED_BATCH => Self::Batch,
+            libc::SCHED_IDLE => Self::Idle,
+            SCHED_DEADLINE => Self::Deadline,
+            _ => Self::Unknown(raw),
+        }
+    }
+    fn to_raw(self) -> u32 {
+        (match self {
+            Self::Other => libc::SCHED_OTHER,
+            Self::Fifo => libc::SCHED_FIFO,
+            Self::RoundRobin => libc::SCHED_RR,
+            Self::Batch => libc::SCHED_BATCH,
+            Self::Idle => libc::SCHED_IDLE,
+            Self::Deadline => SCHED_DEADLINE,
+            Self::Unknown(raw) => return raw,
+        }) as u32
+    }
+    pub fn is_realtime(self) -> bool {
+        matches!(self, Self::Fifo | Self::RoundRobin)
+    }
+}
+impl fmt::Display for SchedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Other => "other",
+            Self::Fifo => "fifo",
+            Self::RoundRobin => "rr",
+            Self::Batch => "batch",
+            Self::Idle => "idle",
+            Self::Deadline => "deadline",
+            Self::Unknown(raw) => return write!(f, "{raw}"),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoClass {
+    /// Derived from the nice value, as best-effort with level `(nice + 20) / 5`.
+    None,
+    RealTime,
+    BestEffort,
+    Idle,
+    /// One added after this was written.
+    Unknown(u32),
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoPriority {
+    pub class: IoClass,
+    /// From 0 (highest) to 7. Only meaningful for `RealTime` and `BestEffort`.
+    pub level: u8,
+}
+impl IoClass {
+    pub const ALL: [Self; 4] = [Self::None, Self::RealTime, Self::BestEffort, Self::Idle];
+    pub fn has_level(self) -> bool {
+        matches!(self, Self::RealTime | Self::BestEffort)
+    }
+}
+impl IoPriority {
+    fn from_raw(raw: u32) -> Self {
+        Self {
+            class: match raw >> IOPRIO_CLASS_SHIFT {
+                0 => IoClass::None,
+                1 => IoClass::RealTime,
+                2 => IoClass::BestEffort,
+                3 => IoClass::Idle,
+                other => IoClass::Unknown(other),
+            },
+            level: (raw & 0x7) as u8,
+        }
+    }
+    fn to_raw(self) -> u32 {
+        let class = match self.class {
+            IoClass::None => 0,
+            IoClass::RealTime => 1,
+            IoClass::BestEffort => 2,
+            IoClass::Idle => 3,
+            IoClass::Unknown(class) => class,
+        };
+        let level = match self.class.has_level() {
+            true => self.level.min(7) as u32,
+            false => 0,
+        };
+        class << IOPRIO_CLASS_SHIFT | level
+    }
+}
+impl fmt::Display for IoClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::RealTime => "rt",
+            Self::BestEffort => "be",
+            Self::Idle => "idle",
+            Self::Unknown(class) => return write!(f, "{class}"),
+        })
+    }
+}
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.class.has_level() {
+            true => write!(f, "{}/{}", self.class, self.level),
+            false => write!(f, "{}", self.class),
+        }
+    }
+}
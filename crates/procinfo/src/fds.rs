@@ -0,0 +1,275 @@
+use crate::procfs::{check_io_err, read_file_to_string};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+#[derive(Debug, Clone)]
+pub struct OpenFile {
+    pub fd: u32,
+    /// Where `/proc/{pid}/fd/{fd}` links to, such as a path or `socket:[1234]`.
+    pub target: String,
+    pub kind: FdKind,
+    /// The `open` flags, such as `O_APPEND`. `None` if `fdinfo` was unreadable.
+    pub flags: Option<u32>,
+    pub pos: Option<u64>,
+    /// Only for sockets found in the network namespace of the process.
+    pub socket: Option<SocketInfo>,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FdKind {
+    File,
+    Socket,
+    Pipe,
+    /// Such as `eventfd`, `eventpoll`, `inotify` or `timerfd`.
+    AnonInode(String),
+    Memfd,
+    Other,
+}
+#[derive(Debug, Clone)]
+pub struct SocketInfo {
+    /// `tcp`, `tcp6`, `udp`, `udp6` or `unix`.
+    pub protocol: &'static str,
+    pub local: String,
+    /// Empty for unconnected sockets.
+    pub remote: String,
+    /// Such as `ESTABLISHED` or `LISTEN`. Empty for unix sockets.
+    pub state: &'static str,
+}
+
+/// Lists the open file descriptors of a process. Returns `None` if it exited, or if we lack
+/// `PTRACE_MODE_READ_FSCREDS` for it.
+pub fn get_open_files(pid: u32) -> Option<Vec<OpenFile>> {
+    let dir = match fs::read_dir(format!("/proc/{pid}/fd")) {
+        Ok(dir) => dir,
+        Err(err) => {
+            check_io_err(err);
+            return None;
+        }
+    };
+    let mut sockets = None;
+    let mut ret: Vec<OpenFile> = dir
+        .filter_map(|entry| {
+            // Entries disappear as the process closes them
+            let entry = entry.ok()?;
+            let fd = entry.file_name().to_str()?.parse().ok()?;
+            let target = fs::read_link(entry.path())
+                .ok()?
+                .to_string_lossy()
+                .into_owned();
+            let kind = FdKind::of(&target);
+            let (flags, pos) = get_flags_pos(pid, fd).unzip();
+            let socket = match kind {
+                FdKind::Socket => {
+                    let inode = target
+                        .strip_prefix("socket:[")
+                        .and_then(|s| s.strip_suffix(']'))
+                        .and_then(|s| s.parse::<u64>().ok());
+                    let sockets = sockets.get_or_insert_with(|| get_sockets(pid));
+                    inode.and_then(|inode| sockets.get(&inode).cloned())
+                }
+                _ => None,
+            };
+            Some(OpenFile {
+                fd,
+                target,
+                kind,
+                flags,
+                pos,
+                socket,
+            })
+        })
+        .collect();
+    ret.sort_by_key(|file| file.fd);
+    Some(ret)
+}
+
+impl FdKind {
+    fn of(target: &str) -> Self {
+        if target.starts_with("socket:") {
+            Self::Socket
+        } else if target.starts_with("pipe:") {
+            Self::Pipe
+        } else if let Some(name) = target.strip_prefix("anon_inode:") {
+            Self::AnonInode(name.trim_matches(['[', ']']).to_owned())
+        } else if target.starts_with("/memfd:") {
+            Self::Memfd
+        } else if target.starts_with('/') {
+            Self::File
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl fmt::Display for FdKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::File => "file",
+            Self::Socket => "socket",
+            Self::Pipe => "pipe",
+            Self::AnonInode(name) => name,
+            Self::Memfd => "memfd",
+            Self::Other => "other",
+        })
+    }
+}
+impl OpenFile {
+    /// The access mode followed by the notable flags, like `rw append cloexec`.
+    pub fn describe_flags(&self) -> String {
+        let Some(flags) = self.flags.map(|flags| flags as libc::c_int) else {
+            return "?".to_owned();
+        };
+        let mut ret = match flags & libc::O_ACCMODE {
+            libc::O_RDONLY => "r",
+            libc::O_WRONLY => "w",
+            _ => "rw",
+        }
+        .to_owned();
+        for (flag, name) in [
+            (libc::O_APPEND, "append"),
+            (libc::O_NONBLOCK, "nonblock"),
+            (libc::O_CLOEXEC, "cloexec"),
+            (libc::O_DIRECT, "direct"),
+            (libc::O_SYNC, "sync"),
+        ] {
+            if flags & flag == flag {
+                ret.push(' ');
+                ret.push_str(name);
+            }
+        }
+        ret
+    }
+}
+
+fn get_flags_pos(pid: u32, fd: u32) -> Option<(u32, u64)> {
+    let mut file = File::open(format!("/proc/{pid}/fdinfo/{fd}"))
+        .map_err(check_io_err)
+        .ok()?;
+    let buf = &mut [0u8; 4096];
+    let data = read_file_to_string(&mut file, buf)?;
+    let mut flags = None;
+    let mut pos = None;
+    for line in data.lines() {
+        match line.split_once(':') {
+            Some(("pos", value)) => pos = Some(value.trim().parse().unwrap()),
+            Some(("flags", value)) => flags = Some(u32::from_str_radix(value.trim(), 8).unwrap()),
+            _ => {}
+        }
+    }
+    Some((flags?, pos?))
+}
+
+/// Sockets by inode, in the network namespace of `pid`.
+fn get_sockets(pid: u32) -> HashMap<u64, SocketInfo> {
+    let mut ret = HashMap::new();
+    for protocol in ["tcp", "tcp6", "udp", "udp6"] {
+        // Unlike the files in `/proc/{pid}`, these are often larger than a page
+        let Some(data) = fs::read_to_string(format!("/proc/{pid}/net/{protocol}"))
+            .map_err(check_io_err)
+            .ok()
+        else {
+            continue;
+        };
+        // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode
+        for line in data.lines().skip(1) {
+            let fields: Vec<&str> = line.split_ascii_whitespace().collect();
+            let Some(Ok(inode)) = fields.get(9).map(|inode| inode.parse()) else {
+                continue;
+            };
+            ret.insert(
+                inode,
+                SocketInfo {
+                    protocol,
+                    local: parse_inet_address(fields[1]),
+                    remote: match fields[2].trim_start_matches(['0', ':']) {
+                        "" => String::new(),
+                        _ => parse_inet_address(fields[2]),
+                    },
+                    state: match protocol.starts_with("tcp") {
+                        true => tcp_state_name(fields[3]),
+                        false => "",
+                    },
+                },
+            );
+        }
+    }
+    // Bound and abstract socket names may be any bytes
+    if let Ok(data) = fs::read(format!("/proc/{pid}/net/unix")).map_err(check_io_err) {
+        let data = String::from_utf8_lossy(&data);
+        // Num RefCount Protocol Flags Type St Inode Path
+        for line in data.lines().skip(1) {
+            // Split by hand, since the inode is padded with spaces and the path may contain them
+            let mut fields = [""; 7];
+            let mut rest = line;
+            for field in &mut fields {
+                let (value, after) = rest
+                    .trim_start_matches(' ')
+                    .split_once(' ')
+                    .unwrap_or((rest.trim_start_matches(' '), ""));
+                *field = value;
+                rest = after;
+            }
+            // Abstract socket names may contain newlines, which leave lines that are only the
+            // rest of a name
+            let Ok(inode) = fields[6].parse() else {
+                continue;
+            };
+            ret.insert(
+                inode,
+                SocketInfo {
+                    protocol: "unix",
+                    local: rest.to_owned(),
+                    remote: String::new(),
+                    state: "",
+                },
+            );
+        }
+    }
+    ret
+}
+
+/// Parses `0100007F:0035` or its IPv6 equivalent. Each 32-bit word of the address is printed
+/// in host byte order, while the port is printed as a number.
+fn parse_inet_address(s: &str) -> String {
+    let (addr, port) = s.split_once(':').unwrap();
+    let port = u16::from_str_radix(port, 16).unwrap();
+    let mut words = addr
+        .as_bytes()
+        .chunks(8)
+        .map(|word| u32::from_str_radix(std::str::from_utf8(word).unwrap(), 16).unwrap());
+    match addr.len() {
+        8 => {
+            let bytes = words.next_back().unwrap().to_ne_bytes();
+            format!("{}:{port}", Ipv4Addr::from(bytes))
+        }
+        32 => {
+            let mut bytes = [0u8; 16];
+            for (chunk, word) in bytes.chunks_mut(4).zip(words) {
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            format!("[{}]:{port}", Ipv6Addr::from(bytes))
+        }
+        other => panic!("unexpected address length {other}"),
+    }
+}
+
+fn tcp_state_name(st: &str) -> &'static str {
+    match st {
+        "01" => "ESTABLISHED",
+        "02" => "SYN_SENT",
+        "03" => "SYN_RECV",
+        "04" => "FIN_WAIT1",
+        "05" => "FIN_WAIT2",
+        "06" => "TIME_WAIT",
+        "07" => "CLOSE",
+        "08" => "CLOSE_WAIT",
+        "09" => "LAST_ACK",
+        "0A" => "LISTEN",
+        "0B" => "CLOSING",
+        "0C" => "NEW_SYN_RECV",
+        _ => "?",
+    }
+}
@@ -0,0 +1,113 @@
+use crate::info::{ProcInfo, ProcessInfo};
+use std::cmp::Ordering;
+
+/// A search query over processes, where every whitespace-separated term must match:
+/// - `pid:1234`
+/// - `user:alice` or `user:1000`
+/// - `state:D`, or `state:DR` for either state
+/// - `cpu>5%` or `cpu<5%`, as a percentage of one core
+/// - `mem>1G` or `mem<100M`, of the resident set
+/// - anything else as a case-insensitive substring of the name or cmdline
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcFilter {
+    terms: Vec<Term>,
+}
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    Text(String),
+    Pid(u32),
+    User(String),
+    State(String),
+    Cpu(Ordering, f64),
+    Mem(Ordering, u64),
+}
+
+impl ProcFilter {
+    /// Returns a description of the first malformed term on failure.
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let terms = query
+            .split_whitespace()
+            .map(Term::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+    pub(crate) fn matches(&self, info: &ProcInfo, p: &ProcessInfo) -> bool {
+        self.terms.iter().all(|term| term.matches(info, p))
+    }
+}
+
+impl Term {
+    fn parse(term: &str) -> Result<Self, String> {
+        if let Some((key, value)) = term.split_once(':') {
+            let parsed = match key {
+                "pid" => value.parse().ok().map(Self::Pid),
+                "user" => Some(Self::User(value.to_owned())),
+                "state" => Some(Self::State(value.to_uppercase())),
+                _ => return Ok(Self::Text(term.to_lowercase())),
+            };
+            return match parsed {
+                Some(parsed) if !value.is_empty() => Ok(parsed),
+                _ => Err(format!("malformed `{term}`")),
+            };
+        }
+        for (key, op) in [
+            ("cpu>", Ordering::Greater),
+            ("cpu<", Ordering::Less),
+            ("mem>", Ordering::Greater),
+            ("mem<", Ordering::Less),
+        ] {
+            let Some(value) = term.strip_prefix(key) else {
+                continue;
+            };
+            let parsed = match key.starts_with("cpu") {
+                true => value
+                    .trim_end_matches('%')
+                    .parse()
+                    .ok()
+                    .map(|percent| Self::Cpu(op, percent)),
+                false => parse_bytes(value).map(|bytes| Self::Mem(op, bytes)),
+            };
+            return parsed.ok_or_else(|| format!("malformed `{term}`"));
+        }
+        Ok(Self::Text(term.to_lowercase()))
+    }
+    fn matches(&self, info: &ProcInfo, p: &ProcessInfo) -> bool {
+        match self {
+            Self::Text(text) => {
+                info.strings.get(p.name).to_lowercase().contains(text)
+                    || p.cmdline
+                        .as_ref()
+                        .is_some_and(|cmdline| cmdline.to_lowercase().contains(text))
+            }
+            Self::Pid(pid) => p.pid == *pid,
+            Self::User(user) => {
+                p.uid.to_string() == *user || info.user(p.uid).is_some_and(|u| u.name == *user)
+            }
+            Self::State(states) => states.contains(p.task.state),
+            Self::Cpu(op, percent) => {
+                let millis = p.stat.user_time_millis + p.stat.system_time_millis;
+                let cpu = millis as f64 * info.update_hz as f64 / 10.0;
+                cpu.partial_cmp(percent) == Some(*op)
+            }
+            Self::Mem(op, bytes) => p.stat.mem_bytes.cmp(bytes) == *op,
+        }
+    }
+}
+
+/// Parses a size like `1G`, `1.5MB` or `4096`, with decimal units like those shown in the UI.
+fn parse_bytes(s: &str) -> Option<u64> {
+    let s = s.to_uppercase();
+    let s = s.strip_suffix('B').unwrap_or(&s);
+    let (number, scale) = match s.char_indices().last()? {
+        (i, 'K') => (&s[..i], 1e3),
+        (i, 'M') => (&s[..i], 1e6),
+        (i, 'G') => (&s[..i], 1e9),
+        (i, 'T') => (&s[..i], 1e12),
+        _ => (s, 1.0),
+    };
+    let number: f64 = number.parse().ok()?;
+    (number >= 0.0).then_some((number * scale) as u64)
+}
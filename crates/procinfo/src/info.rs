@@ -1,42 +1,146 @@
-use crate::ingest::ProcIngest;
+use crate::{
+    apps::DesktopEntries,
+    cgroupfs,
+    container::ContainerId,
+    control::{self, IoPriority, SchedPolicy},
+    filter::ProcFilter,
+    ingest::{CgroupIngest, LoginSessionIngest, ProcIngest, ProcessIngest},
+    procfs::{self, Namespaces},
+    users::{GroupInfo, UserCache, UserInfo},
+};
+use either::Either;
+use nix::sched::CpuSet;
 use std::{
-    collections::HashMap,
-    fs,
-    ops::{Add, AddAssign},
+    borrow::Borrow,
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::Hash,
+    mem,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
 };
+use sysinfo::Series;
+use util::PROC_HISTORY;
+
+/// The capacity of `ProcInfo::exited`.
+const EXITED_HISTORY: usize = 1000;
 
 #[derive(Debug)]
 pub struct ProcInfo {
-    update_hz: u8,
+    pub(crate) update_hz: u8,
+    /// In seconds since the Unix epoch.
+    boot_time: u64,
     sort_by: ProcSortBy,
-    pub uid_to_user: HashMap<u16, UserInfo>,
-    pub gid_to_group: HashMap<u16, GroupInfo>,
+    filter: ProcFilter,
+    /// Every process and thread, and every login session, cgroup and container without their
+    /// stats, from which the rows of the filtered levels below are made. All are updated in
+    /// place, by the changes of each update.
+    all_processes: BTreeMap<u32, ProcessInfo>,
+    all_threads: BTreeMap<u32, ThreadInfo>,
+    all_login_sessions: BTreeMap<Lsid, LoginSessionInfo>,
+    all_cgroups: BTreeMap<String, CgroupInfo>,
+    all_containers: BTreeMap<ContainerId, ContainerInfo>,
+    /// The rows of the filtered levels by key, through which only the rows of changed processes
+    /// are updated.
+    tally: Tallies,
+    /// The sid and pid of every filtered process, to name sessions by.
+    session_members: BTreeSet<(u32, u32)>,
+    /// The entry of each of `processes` in `process_tree`, and the parent entry of each entry,
+    /// to update subtree stats by.
+    tree_entries: Vec<usize>,
+    tree_parents: Vec<Option<usize>>,
+    accounts: UserCache,
+    desktop_entries: DesktopEntries,
+    /// Most threads share a handful of affinities.
+    affinity_strings: HashMap<CpuSet, StringArenaHandle>,
+    /// Kept across updates, so that a name is interned once for as long as it is in use.
     pub strings: StringArena,
     pub login_sessions: Vec<LoginSessionInfo>,
     pub sessions: Vec<SessionInfo>,
     pub processes: Vec<ProcessInfo>,
     pub threads: Vec<ThreadInfo>,
+    pub cgroups: Vec<CgroupInfo>,
+    /// Depth-first, with siblings in the sort order.
+    pub units: Vec<UnitInfo>,
+    pub users: Vec<UserUsage>,
+    pub groups: Vec<GroupUsage>,
+    pub containers: Vec<ContainerInfo>,
+    pub apps: Vec<AppInfo>,
+    /// Every process in depth-first order, with siblings in the order of `processes`.
+    pub process_tree: Vec<ProcessTreeEntry>,
+    /// Kept across updates for as long as the pid is alive.
+    pub history_by_pid: HashMap<u32, ProcessHistory>,
+    /// The most recently exited processes, oldest first, regardless of the filter.
+    pub exited: VecDeque<ExitedProcessInfo>,
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcSortBy {
     Id,
     Name,
     Cpu,
+    ChildrenCpu,
     DiskRead,
     DiskWrite,
+    NetSend,
+    NetReceive,
     Memory,
+    Pss,
+    Uss,
+    RssAnon,
+    RssFile,
+    RssShmem,
+    Swap,
+    MinorFaults,
+    MajorFaults,
+    VoluntarySwitches,
+    InvoluntarySwitches,
+    RunDelay,
+    Gpu,
+    Vram,
+    // Only cgroups are sorted by these kernel stats, with the other levels sorted by id
+    CgroupCpu,
+    CgroupMemory,
+    CgroupRead,
+    CgroupWrite,
+    CgroupPids,
+    // Only processes and threads are sorted by these, with the other levels sorted by id
+    State,
+    /// Most recently started first.
+    StartTime,
+    /// Longest running first.
+    Elapsed,
+    Tty,
+    Ppid,
+    Nice,
+    Threads,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LoginSessionInfo {
     pub lsid: Lsid,
     pub name: String,
     pub stat: ProcStat,
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// A row of any level that processes can be looked up by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcTarget {
+    LoginSession(Lsid),
+    Session(u32),
+    Process(u32),
+    Cgroup(String),
+    /// The cgroup path of a systemd unit.
+    Unit(String),
+    User(u32),
+    Group(u32),
+    Container(ContainerId),
+    /// A desktop application id.
+    App(String),
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Lsid {
     Kernel,
     SystemdServices,
-    SystemdSession(u16),
+    SystemdSession(u32),
 }
 #[derive(Debug)]
 pub struct SessionInfo {
@@ -46,155 +150,1092 @@ pub struct SessionInfo {
     pub entries_cmdline: String,
     pub stat: ProcStat,
 }
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ProcessInfo {
+    pub parent_lsid: Lsid,
     pub parent_sid: u32,
     pub pid: u32,
-    pub uid: u16,
-    pub gid: u16,
+    pub uid: u32,
+    pub gid: u32,
     pub name: StringArenaHandle,
     pub cmdline: Option<String>,
+    pub cgroup: Option<String>,
+    /// The innermost systemd service or scope, such as `sshd.service`.
+    pub unit: Option<String>,
+    pub container: Option<ContainerId>,
+    /// The pid as seen from within its container, if in a pid namespace other than ours.
+    pub ns_pid: Option<u32>,
+    /// The desktop application id, such as `org.gnome.Terminal`.
+    pub app: Option<String>,
+    pub task: TaskInfo,
+    pub sched: SchedInfo,
     pub stat: ProcStat,
 }
+/// What a process amounted to, as of the last update it was alive for.
+#[derive(Debug, Clone)]
+pub struct ExitedProcessInfo {
+    pub pid: u32,
+    pub uid: u32,
+    pub name: String,
+    pub cmdline: Option<String>,
+    /// In milliseconds since the Unix epoch.
+    pub start_time_ms: u64,
+    /// When the exit was noticed, which is at most one update after the actual exit.
+    pub end_time_ms: u64,
+    pub cpu_time_ms: u64,
+    pub peak_mem_bytes: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+/// The recent past of a process, one sample per update.
+#[derive(Debug, Default)]
+pub struct ProcessHistory {
+    /// In CPUs, so that 1.0 is one fully busy core.
+    pub cpu: Series<f64, PROC_HISTORY>,
+    pub mem_bytes: Series<f64, PROC_HISTORY>,
+    pub disk_read_bytes_per_second: Series<f64, PROC_HISTORY>,
+    pub disk_write_bytes_per_second: Series<f64, PROC_HISTORY>,
+    pub net_send_bytes_per_second: Series<f64, PROC_HISTORY>,
+    pub net_receive_bytes_per_second: Series<f64, PROC_HISTORY>,
+}
 #[derive(Debug)]
+pub struct ProcessTreeEntry {
+    /// Index into `ProcInfo::processes`.
+    pub idx: usize,
+    pub depth: usize,
+    /// The descendants are the entries directly following this one.
+    pub num_descendants: usize,
+    /// Summed over the process and all its descendants.
+    pub subtree_stat: ProcStat,
+}
+#[derive(Debug, Clone, PartialEq)]
 pub struct ThreadInfo {
     pub parent_pid: u32,
     pub tid: u32,
     pub name: StringArenaHandle,
+    pub task: TaskInfo,
+    pub sched: SchedInfo,
+    pub stat: ProcStat,
+}
+/// The state and origin of a thread, or of the main thread of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskInfo {
+    /// Such as `R` for running or `D` for uninterruptible sleep.
+    pub state: char,
+    pub ppid: u32,
+    /// In milliseconds since the Unix epoch.
+    pub start_time_ms: u64,
+    /// The device number of the controlling terminal, or zero if there is none.
+    pub tty_nr: u32,
+    /// Of the whole process.
+    pub num_threads: u32,
+}
+/// Scheduling parameters of a thread, or of the main thread of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedInfo {
+    pub nice: i8,
+    pub policy: SchedPolicy,
+    /// Nonzero only for the realtime policies.
+    pub rt_priority: u8,
+    pub io_priority: Option<IoPriority>,
+    /// The CPUs the thread may run on, formatted like `0-3,8`.
+    pub affinity: Option<StringArenaHandle>,
+}
+/// The processes owned by a uid, named after its passwd entry if there is one.
+#[derive(Debug, Clone)]
+pub struct UserUsage {
+    pub uid: u32,
+    pub name: String,
+    pub stat: ProcStat,
+}
+/// The processes whose primary group is a gid, named after its group entry if there is one.
+#[derive(Debug, Clone)]
+pub struct GroupUsage {
+    pub gid: u32,
+    pub name: String,
     pub stat: ProcStat,
 }
-#[derive(Clone, Copy, Debug)]
+#[derive(Debug, Clone)]
+pub struct ContainerInfo {
+    pub id: ContainerId,
+    /// `None` if unknown, as when in our uts or mount namespace.
+    pub hostname: Option<String>,
+    /// `None` if unreadable.
+    pub namespaces: Option<Namespaces>,
+    pub stat: ProcStat,
+}
+/// The processes of a desktop application, named after its `.desktop` file if there is one.
+#[derive(Debug, Clone)]
+pub struct AppInfo {
+    /// The desktop file id, such as `org.gnome.Terminal`.
+    pub id: String,
+    pub name: String,
+    /// A PNG file.
+    pub icon: Option<PathBuf>,
+    pub stat: ProcStat,
+}
+/// A systemd slice, service or scope, as found in cgroup paths.
+#[derive(Debug, Clone)]
+pub struct UnitInfo {
+    /// The cgroup path, such as `/system.slice/sshd.service`.
+    pub path: String,
+    /// Such as `sshd.service`.
+    pub name: String,
+    /// The number of slices and units containing it.
+    pub depth: usize,
+    pub stat: ProcStat,
+}
+#[derive(Debug, Clone)]
+pub struct CgroupInfo {
+    /// The inode number of the cgroup directory, or zero if unavailable.
+    pub id: u64,
+    pub path: String,
+    /// Summed over every process in the cgroup and its descendants.
+    pub stat: ProcStat,
+    pub kernel_stat: CgroupStat,
+}
+/// The kernel's accounting of a cgroup, which includes exited processes and descendant cgroups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CgroupStat {
+    pub user_time_millis: u32,
+    pub system_time_millis: u32,
+    pub io_read_bytes_per_second: u64,
+    pub io_write_bytes_per_second: u64,
+    pub mem_bytes: u64,
+    pub pids: u64,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct ProcStat {
     pub guest_time_millis: u32,
     pub user_time_millis: u32,
     pub system_time_millis: u32,
+    /// User and system time of reaped children. Unlike the other fields, this counts time again
+    /// that was already counted for the children while they were alive.
+    pub children_time_millis: u32,
     pub disk_read_bytes_per_second: u64,
     pub disk_write_bytes_per_second: u64,
+    /// TCP payload, and zero for threads, as the sockets belong to the process.
+    pub net_send_bytes_per_second: u64,
+    pub net_receive_bytes_per_second: u64,
+    pub minor_faults_per_second: u64,
+    pub major_faults_per_second: u64,
+    /// Such as when blocking on I/O or a lock.
+    pub voluntary_switches_per_second: u64,
+    /// Preempted while still runnable, because the time slice ran out.
+    pub involuntary_switches_per_second: u64,
+    /// Time spent runnable but waiting for a CPU, like `user_time_millis`.
+    pub run_delay_millis: u32,
+    /// GPU engine time, summed over engines like `user_time_millis` is over CPUs. Zero for
+    /// threads, as the driver accounts it per process.
+    pub gpu_time_millis: u32,
+    /// Resident set size. Counts shared pages once per process mapping them.
     pub mem_bytes: u64,
+    /// Proportional set size, zero unless `smaps_rollup` is enabled. Sums to actual memory use.
+    pub pss_bytes: u64,
+    /// Unique set size, zero unless `smaps_rollup` is enabled.
+    pub uss_bytes: u64,
+    pub rss_anon_bytes: u64,
+    pub rss_file_bytes: u64,
+    pub rss_shmem_bytes: u64,
+    pub swap_bytes: u64,
+    /// GPU memory resident in VRAM.
+    pub gpu_vram_bytes: u64,
+}
+/// The rows of one filtered level by key, with the number of filtered processes in each.
+#[derive(Debug)]
+struct Tally<K> {
+    /// The position of each row, and its number of processes. Rows left without any are only
+    /// removed by `remove_emptied`, so that those regaining one meanwhile keep their position.
+    rows: HashMap<K, (usize, usize)>,
+    /// Positions of rows that were left without processes.
+    emptied: Vec<usize>,
+    /// Whether any row was pushed since `take_pushed`.
+    pushed: bool,
+}
+#[derive(Debug, Default)]
+struct Tallies {
+    login_sessions: Tally<Lsid>,
+    sessions: Tally<u32>,
+    processes: Tally<u32>,
+    threads: Tally<u32>,
+    cgroups: Tally<String>,
+    units: Tally<String>,
+    users: Tally<u32>,
+    groups: Tally<u32>,
+    containers: Tally<ContainerId>,
+    apps: Tally<String>,
+}
+/// What applying changed processes and threads to the filtered levels did, which decides
+/// whether they need sorting again.
+#[derive(Debug, Default)]
+struct LevelChanges {
+    /// Whether a value some sort order depends on changed in place.
+    names: bool,
+    tasks: bool,
+    stats: bool,
+    kernel_stats: bool,
+    /// Whether a filtered process changed parent, and its subtree with it.
+    reparented: bool,
+    /// The pid and stat before and after of each filtered process that changed in place.
+    process_stats: Vec<(u32, ProcStat, ProcStat)>,
+    /// Sessions that gained or lost a process, or one of which was renamed.
+    sessions: BTreeSet<u32>,
+}
+/// A row of a filtered level, kept in a `Tally` by its key.
+trait Row {
+    type Key: ?Sized + Hash + Eq;
+    fn key(&self) -> &Self::Key;
 }
 
 impl ProcInfo {
     pub fn new() -> Self {
-        let uid_to_user = fs::read_to_string("/etc/passwd")
-            .unwrap()
-            .lines()
-            .map(|line| {
-                let user = UserInfo::new(line);
-                (user.uid, user)
-            })
-            .collect();
-        let gid_to_group = fs::read_to_string("/etc/group")
-            .unwrap()
-            .lines()
-            .map(|line| {
-                let group = GroupInfo::new(line);
-                (group.gid, group)
-            })
-            .collect();
         Self {
             update_hz: 1,
+            boot_time: procfs::get_boot_time(),
             sort_by: ProcSortBy::Id,
-            uid_to_user,
-            gid_to_group,
+            filter: ProcFilter::default(),
+            all_processes: BTreeMap::new(),
+            all_threads: BTreeMap::new(),
+            all_login_sessions: BTreeMap::from([
+                (
+                    Lsid::Kernel,
+                    LoginSessionInfo {
+                        lsid: Lsid::Kernel,
+                        name: "kernel".to_owned(),
+                        stat: ProcStat::ZERO,
+                    },
+                ),
+                (
+                    Lsid::SystemdServices,
+                    LoginSessionInfo {
+                        lsid: Lsid::SystemdServices,
+                        name: "system services".to_owned(),
+                        stat: ProcStat::ZERO,
+                    },
+                ),
+            ]),
+            all_cgroups: BTreeMap::new(),
+            all_containers: BTreeMap::new(),
+            tally: Tallies::default(),
+            session_members: BTreeSet::new(),
+            tree_entries: Vec::new(),
+            tree_parents: Vec::new(),
+            accounts: UserCache::default(),
+            desktop_entries: DesktopEntries::load(),
+            affinity_strings: HashMap::new(),
             strings: StringArena::default(),
 
             login_sessions: Vec::new(),
             sessions: Vec::new(),
             processes: Vec::new(),
             threads: Vec::new(),
+            cgroups: Vec::new(),
+            units: Vec::new(),
+            users: Vec::new(),
+            groups: Vec::new(),
+            containers: Vec::new(),
+            apps: Vec::new(),
+            process_tree: Vec::new(),
+            history_by_pid: HashMap::new(),
+            exited: VecDeque::with_capacity(EXITED_HISTORY),
         }
     }
     pub fn update(&mut self, src: &ProcIngest) {
-        self.strings = StringArena::default();
-        self.login_sessions = Vec::new();
-        self.sessions = Vec::new();
-        self.processes = Vec::new();
-        self.threads = Vec::new();
-        for (&pid, process) in &src.by_pid {
-            let thread_start_idx = self.threads.len();
-            let name = self.strings.push(process.name.clone());
+        let names_received = self.accounts.receive();
+        self.update_aggregates(src, names_received);
+        // Each process to apply to the filtered levels, as it was before if it was alive
+        let mut changed_processes: Vec<(u32, Option<ProcessInfo>)> = src
+            .removed_pids
+            .iter()
+            .map(|&pid| (pid, self.all_processes.remove(&pid)))
+            .collect();
+        let mut changed_tids = Vec::new();
+        // Threads mostly share one affinity, which is quicker to compare than to hash
+        let mut last_affinity: Option<(CpuSet, StringArenaHandle)> = None;
+        for &pid in &src.changed_pids {
+            let process = &src.by_pid[&pid];
+            self.accounts.resolve_user(process.uid);
+            self.accounts.resolve_group(process.gid);
+            let mem = ProcStat::memory_of(process);
+            let mut threads_stat = ProcStat::ZERO;
             for (&tid, thread) in &process.by_tid {
-                self.threads.push(ThreadInfo {
+                let affinity = thread.affinity.map(|cpus| match last_affinity {
+                    Some((last, handle)) if last == cpus => handle,
+                    _ => {
+                        let handle = *self.affinity_strings.entry(cpus).or_insert_with(|| {
+                            self.strings.intern(&control::format_cpu_list(&cpus))
+                        });
+                        last_affinity = Some((cpus, handle));
+                        handle
+                    }
+                });
+                // Kernel threads are named like their process, but without brackets
+                let name = match process.kernel {
+                    true => &process.name,
+                    false => &thread.name,
+                };
+                let task = TaskInfo {
+                    state: thread.state,
+                    ppid: thread.ppid,
+                    start_time_ms: self.boot_time * 1000 + thread.start_time_ms,
+                    tty_nr: thread.tty_nr,
+                    num_threads: thread.num_threads,
+                };
+                let sched = SchedInfo {
+                    nice: thread.nice,
+                    policy: SchedPolicy::from_raw(thread.policy),
+                    rt_priority: thread.rt_priority,
+                    io_priority: thread.io_priority,
+                    affinity,
+                };
+                let stat = ProcStat {
+                    guest_time_millis: thread.guest_time_ms,
+                    user_time_millis: thread.user_time_ms,
+                    system_time_millis: thread.system_time_ms,
+                    disk_read_bytes_per_second: thread.read_bytes / self.update_hz as u64,
+                    disk_write_bytes_per_second: thread.write_bytes / self.update_hz as u64,
+                    minor_faults_per_second: thread.minor_faults / self.update_hz as u64,
+                    major_faults_per_second: thread.major_faults / self.update_hz as u64,
+                    voluntary_switches_per_second: thread.voluntary_switches
+                        / self.update_hz as u64,
+                    involuntary_switches_per_second: thread.involuntary_switches
+                        / self.update_hz as u64,
+                    run_delay_millis: (thread.run_delay_ns / 1_000_000) as u32,
+                    ..mem
+                };
+                threads_stat += stat;
+                let name = match self.all_threads.get(&tid) {
+                    Some(t) if self.strings.get(t.name) == name => t.name,
+                    _ => self.strings.intern(name),
+                };
+                let thread = ThreadInfo {
                     parent_pid: pid,
                     tid,
                     name,
-                    stat: ProcStat {
-                        guest_time_millis: thread.guest_time_ms,
-                        user_time_millis: thread.user_time_ms,
-                        system_time_millis: thread.system_time_ms,
-                        disk_read_bytes_per_second: thread.read_bytes / self.update_hz as u64,
-                        disk_write_bytes_per_second: thread.write_bytes / self.update_hz as u64,
-                        mem_bytes: process.vm_rss_kb * 1024,
-                    },
-                });
+                    task,
+                    sched,
+                    stat,
+                };
+                // Replacing a reused tid as well
+                if self.all_threads.get(&tid) != Some(&thread) {
+                    self.all_threads.insert(tid, thread);
+                    changed_tids.push(tid);
+                }
             }
-            self.processes.push(ProcessInfo {
-                parent_sid: process.by_tid.first_key_value().unwrap().1.sid,
+            let (main_tid, main_thread) = process.by_tid.first_key_value().unwrap();
+            let &ThreadInfo { task, sched, .. } = &self.all_threads[main_tid];
+            let parent_sid = main_thread.sid;
+            let parent_lsid = match (parent_sid, process.login_session) {
+                (0, _) => Lsid::Kernel,
+                (_, Some(id)) => Lsid::SystemdSession(id),
+                (_, None) => Lsid::SystemdServices,
+            };
+            let stat = threads_stat
+                .with_process_times_of(process)
+                .with_open_files_of(process, self.update_hz)
+                .with_memory_of(mem);
+            let name = match self.all_processes.get(&pid) {
+                Some(p) if self.strings.get(p.name) == process.name => p.name,
+                _ => self.strings.intern(&process.name),
+            };
+            let unit = process
+                .cgroup
+                .as_deref()
+                .and_then(|cgroup| cgroupfs::unit_paths(cgroup).next())
+                .map(cgroupfs::unit_name);
+            let p = ProcessInfo {
+                parent_lsid,
+                parent_sid,
                 pid,
                 uid: process.uid,
                 gid: process.gid,
                 name,
                 cmdline: process.cmdline.clone(),
-                stat: {
-                    let mut stat = self.threads[thread_start_idx..]
-                        .iter()
-                        .map(|thread| thread.stat)
-                        .fold(ProcStat::ZERO, ProcStat::add);
-                    stat.mem_bytes = process.vm_rss_kb * 1024;
-                    stat
-                },
+                cgroup: process.cgroup.clone(),
+                unit,
+                container: process.container.clone(),
+                ns_pid: process.ns_pid,
+                // Assigned below
+                app: None,
+                task,
+                sched,
+                stat,
+            };
+            changed_processes.push((pid, self.all_processes.insert(pid, p)));
+        }
+        for &tid in &src.removed_tids {
+            // Unless since reused by a changed process
+            let reused = self.all_threads.get(&tid).is_some_and(|t| {
+                src.by_pid
+                    .get(&t.parent_pid)
+                    .is_some_and(|process| process.by_tid.contains_key(&tid))
             });
+            if !reused {
+                self.all_threads.remove(&tid);
+            }
+            changed_tids.push(tid);
         }
-        self.processes.sort_by_key(|p| (p.parent_sid, p.pid));
-        for p in &self.processes {
-            if self
-                .sessions
-                .last()
-                .map_or(true, |session| session.sid != p.parent_sid)
-            {
-                self.sessions.push(SessionInfo {
-                    parent_lsid: if p.parent_sid == 0 {
-                        Lsid::Kernel
-                    } else {
-                        Lsid::SystemdServices
-                    },
-                    sid: p.parent_sid,
-                    name: p.name,
-                    entries_cmdline: String::new(),
+        self.assign_apps(&mut changed_processes);
+
+        let mut changes = LevelChanges::default();
+        for (pid, old) in changed_processes {
+            let new = self.all_processes.remove(&pid);
+            // As counted in the filtered levels, if at all
+            let old = old.filter(|_| self.tally.processes.contains(&pid));
+            let filtered = new.as_ref().filter(|p| self.filter.matches(self, p));
+            // Their threads are then shown or hidden along with it
+            if filtered.is_some() != old.is_some() {
+                if let Some(process) = src.by_pid.get(&pid) {
+                    changed_tids.extend(process.by_tid.keys());
+                }
+            }
+            self.apply_process(old.as_ref(), filtered, &mut changes);
+            if let Some(new) = new {
+                self.all_processes.insert(pid, new);
+            }
+        }
+        for tid in changed_tids {
+            self.apply_thread(tid, &mut changes);
+        }
+        changes.names |= self.name_sessions(&changes.sessions);
+        let processes_removed = self.remove_emptied_rows();
+        self.update_aggregate_rows(names_received, &mut changes);
+
+        for pid in &src.removed_pids {
+            self.history_by_pid.remove(pid);
+        }
+        let now_ms = unix_time_ms();
+        for (pid, process) in &src.exited {
+            // A reused pid starts over
+            self.history_by_pid.remove(pid);
+            if self.exited.len() == EXITED_HISTORY {
+                self.exited.pop_front();
+            }
+            self.exited.push_back(ExitedProcessInfo {
+                pid: *pid,
+                uid: process.uid,
+                name: process.name.clone(),
+                cmdline: process.cmdline.clone(),
+                start_time_ms: self.boot_time * 1000 + process.start_time_ms,
+                end_time_ms: now_ms,
+                cpu_time_ms: process.cumulative_user_time_ms + process.cumulative_system_time_ms,
+                peak_mem_bytes: process.peak_mem_kb * 1024,
+                read_bytes: process.cumulative_read_bytes,
+                write_bytes: process.cumulative_write_bytes,
+            });
+        }
+        for p in self.all_processes.values() {
+            let history = self.history_by_pid.entry(p.pid).or_default();
+            let cpu_millis = p.stat.user_time_millis + p.stat.system_time_millis;
+            history
+                .cpu
+                .push(cpu_millis as f64 * self.update_hz as f64 / 1000.0);
+            history.mem_bytes.push(p.stat.mem_bytes as f64);
+            history
+                .disk_read_bytes_per_second
+                .push(p.stat.disk_read_bytes_per_second as f64);
+            history
+                .disk_write_bytes_per_second
+                .push(p.stat.disk_write_bytes_per_second as f64);
+            history
+                .net_send_bytes_per_second
+                .push(p.stat.net_send_bytes_per_second as f64);
+            history
+                .net_receive_bytes_per_second
+                .push(p.stat.net_receive_bytes_per_second as f64);
+        }
+        // Every handle in use is that of a name or affinity of a process or thread
+        if self.strings.len() > 2 * (self.all_processes.len() + self.all_threads.len()) + 1024 {
+            self.compact_strings();
+        }
+
+        let tree_outdated = self.tally.processes.pushed || processes_removed || changes.reparented;
+        // Removing rows keeps the others in order
+        let reorder = self.tally.take_pushed()
+            || match self.sort_by {
+                ProcSortBy::Id => false,
+                ProcSortBy::Name => changes.names,
+                ProcSortBy::CgroupCpu
+                | ProcSortBy::CgroupMemory
+                | ProcSortBy::CgroupRead
+                | ProcSortBy::CgroupWrite
+                | ProcSortBy::CgroupPids => changes.kernel_stats,
+                ProcSortBy::State
+                | ProcSortBy::StartTime
+                | ProcSortBy::Elapsed
+                | ProcSortBy::Tty
+                | ProcSortBy::Ppid
+                | ProcSortBy::Nice
+                | ProcSortBy::Threads => changes.tasks,
+                _ => changes.stats,
+            };
+        let reordered = reorder && self.sort_levels();
+        if tree_outdated || reordered {
+            self.build_process_tree();
+        } else {
+            self.update_subtree_stats(&changes.process_stats);
+        }
+    }
+    /// Updates the login sessions, cgroups and containers that rows are made from to those of
+    /// `src`, renaming login sessions if `names_received`.
+    fn update_aggregates(&mut self, src: &ProcIngest, names_received: bool) {
+        self.all_login_sessions.retain(|lsid, _| match lsid {
+            Lsid::SystemdSession(id) => src.by_login_session.contains_key(id),
+            Lsid::Kernel | Lsid::SystemdServices => true,
+        });
+        for (&id, session) in &src.by_login_session {
+            let lsid = Lsid::SystemdSession(id);
+            if !names_received && self.all_login_sessions.contains_key(&lsid) {
+                continue;
+            }
+            if let Some(uid) = session.uid {
+                self.accounts.resolve_user(uid);
+            }
+            let name = self.login_session_name(session);
+            self.all_login_sessions.insert(
+                lsid,
+                LoginSessionInfo {
+                    lsid,
+                    name,
                     stat: ProcStat::ZERO,
-                });
+                },
+            );
+        }
+        self.all_cgroups
+            .retain(|path, _| src.by_cgroup.contains_key(path));
+        for (path, cgroup) in &src.by_cgroup {
+            let id = cgroup.files.as_ref().map_or(0, |files| files.id);
+            let kernel_stat = CgroupStat::new(cgroup, self.update_hz);
+            match self.all_cgroups.get_mut(path) {
+                Some(cg) => {
+                    cg.id = id;
+                    cg.kernel_stat = kernel_stat;
+                }
+                None => {
+                    let cg = CgroupInfo {
+                        id,
+                        path: path.clone(),
+                        stat: ProcStat::ZERO,
+                        kernel_stat,
+                    };
+                    self.all_cgroups.insert(path.clone(), cg);
+                }
             }
-            let sess = self.sessions.last_mut().unwrap();
-            if let Some(cmdline) = p.cmdline.as_ref() {
-                if !sess.entries_cmdline.is_empty() {
-                    sess.entries_cmdline.push_str("\n");
+        }
+        self.all_containers
+            .retain(|id, _| src.by_container.contains_key(id));
+        for (id, container) in &src.by_container {
+            match self.all_containers.get_mut(id) {
+                Some(c) => {
+                    if c.hostname != container.hostname {
+                        c.hostname.clone_from(&container.hostname);
+                    }
+                    c.namespaces = container.namespaces;
+                }
+                None => {
+                    let c = ContainerInfo {
+                        id: id.clone(),
+                        hostname: container.hostname.clone(),
+                        namespaces: container.namespaces,
+                        stat: ProcStat::ZERO,
+                    };
+                    self.all_containers.insert(id.clone(), c);
                 }
-                sess.entries_cmdline.push_str(cmdline);
             }
-            sess.stat += p.stat;
         }
-        self.login_sessions = vec![
-            LoginSessionInfo {
-                lsid: Lsid::Kernel,
-                name: "?".to_owned(),
-                stat: ProcStat::ZERO,
-            },
-            LoginSessionInfo {
-                lsid: Lsid::SystemdServices,
-                name: "?".to_owned(),
+    }
+    /// Brings the names and kernel stats of the aggregate rows up to date with what they are
+    /// made from.
+    fn update_aggregate_rows(&mut self, names_received: bool, changes: &mut LevelChanges) {
+        if names_received {
+            for ls in &mut self.login_sessions {
+                ls.name.clone_from(&self.all_login_sessions[&ls.lsid].name);
+            }
+            for u in &mut self.users {
+                u.name = self.accounts.user_name(u.uid);
+            }
+            for g in &mut self.groups {
+                g.name = self.accounts.group_name(g.gid);
+            }
+            changes.names = true;
+        }
+        for cg in &mut self.cgroups {
+            let all = &self.all_cgroups[&cg.path];
+            changes.kernel_stats |= cg.kernel_stat != all.kernel_stat;
+            cg.id = all.id;
+            cg.kernel_stat = all.kernel_stat;
+        }
+        for c in &mut self.containers {
+            let all = &self.all_containers[&c.id];
+            if c.hostname != all.hostname {
+                c.hostname.clone_from(&all.hostname);
+                changes.names = true;
+            }
+            c.namespaces = all.namespaces;
+        }
+    }
+    /// Moves a process in the filtered levels from how it was counted in them, if it was, to
+    /// how it now is, if it passes the filter.
+    fn apply_process(
+        &mut self,
+        old: Option<&ProcessInfo>,
+        new: Option<&ProcessInfo>,
+        changes: &mut LevelChanges,
+    ) {
+        if old == new {
+            return;
+        }
+        if let (Some(old), Some(new)) = (old, new) {
+            changes.names |= old.name != new.name;
+            changes.tasks |= old.task != new.task || old.sched != new.sched;
+            changes.reparented |= old.task.ppid != new.task.ppid;
+            changes.process_stats.push((new.pid, old.stat, new.stat));
+        }
+        changes.stats = true;
+        fn session_entry(p: &ProcessInfo) -> (u32, StringArenaHandle, Option<&str>) {
+            (p.parent_sid, p.name, p.cmdline.as_deref())
+        }
+        if old.map(session_entry) != new.map(session_entry) {
+            changes.sessions.extend(old.map(|p| p.parent_sid));
+            changes.sessions.extend(new.map(|p| p.parent_sid));
+        }
+        if let Some(old) = old {
+            self.count_out(old);
+        }
+        if let Some(new) = new {
+            self.count_in(new);
+        }
+    }
+    /// Counts `p` in every filtered level, pushing the rows it is the first process of.
+    fn count_in(&mut self, p: &ProcessInfo) {
+        let t = &mut self.tally;
+        let i = t
+            .processes
+            .count_in(&mut self.processes, &p.pid, || p.clone());
+        self.processes[i].clone_from(p);
+        let i = t
+            .sessions
+            .count_in(&mut self.sessions, &p.parent_sid, || SessionInfo {
+                parent_lsid: p.parent_lsid,
+                sid: p.parent_sid,
+                name: p.name,
+                entries_cmdline: String::new(),
                 stat: ProcStat::ZERO,
-            },
-        ];
-        for s in &self.sessions {
-            let idx = match s.parent_lsid {
-                Lsid::Kernel => 0,
-                Lsid::SystemdServices => 1,
-                Lsid::SystemdSession(_) => unreachable!(),
+            });
+        self.sessions[i].stat += p.stat;
+        self.session_members.insert((p.parent_sid, p.pid));
+        let i = t
+            .login_sessions
+            .count_in(&mut self.login_sessions, &p.parent_lsid, || {
+                self.all_login_sessions[&p.parent_lsid].clone()
+            });
+        self.login_sessions[i].stat += p.stat;
+        if let Some(cgroup) = p.cgroup.as_deref() {
+            let mut path = Some(cgroup);
+            while let Some(cgroup) = path {
+                let i = t.cgroups.count_in(&mut self.cgroups, cgroup, || {
+                    self.all_cgroups[cgroup].clone()
+                });
+                self.cgroups[i].stat += p.stat;
+                path = cgroupfs::parent_path(cgroup);
+            }
+            for path in cgroupfs::unit_paths(cgroup) {
+                let i = t
+                    .units
+                    .count_in(&mut self.units, path, || UnitInfo::new(path));
+                self.units[i].stat += p.stat;
+            }
+        }
+        let i = t.users.count_in(&mut self.users, &p.uid, || UserUsage {
+            uid: p.uid,
+            name: self.accounts.user_name(p.uid),
+            stat: ProcStat::ZERO,
+        });
+        self.users[i].stat += p.stat;
+        let i = t.groups.count_in(&mut self.groups, &p.gid, || GroupUsage {
+            gid: p.gid,
+            name: self.accounts.group_name(p.gid),
+            stat: ProcStat::ZERO,
+        });
+        self.groups[i].stat += p.stat;
+        if let Some(id) = &p.container {
+            let i = t
+                .containers
+                .count_in(&mut self.containers, id, || self.all_containers[id].clone());
+            self.containers[i].stat += p.stat;
+        }
+        if let Some(id) = &p.app {
+            let i = t.apps.count_in(&mut self.apps, id, || {
+                let entry = self.desktop_entries.get(id);
+                AppInfo {
+                    id: id.clone(),
+                    name: entry.map_or_else(|| id.clone(), |entry| entry.name.clone()),
+                    icon: entry.and_then(|entry| entry.icon.clone()),
+                    stat: ProcStat::ZERO,
+                }
+            });
+            self.apps[i].stat += p.stat;
+        }
+    }
+    /// Undoes `count_in` of `p`, leaving rows without processes to `remove_emptied_rows`.
+    fn count_out(&mut self, p: &ProcessInfo) {
+        let t = &mut self.tally;
+        t.processes.count_out(&self.processes, &p.pid);
+        let i = t.sessions.count_out(&self.sessions, &p.parent_sid);
+        self.sessions[i].stat -= p.stat;
+        self.session_members.remove(&(p.parent_sid, p.pid));
+        let i = t
+            .login_sessions
+            .count_out(&self.login_sessions, &p.parent_lsid);
+        self.login_sessions[i].stat -= p.stat;
+        if let Some(cgroup) = p.cgroup.as_deref() {
+            let mut path = Some(cgroup);
+            while let Some(cgroup) = path {
+                let i = t.cgroups.count_out(&self.cgroups, cgroup);
+                self.cgroups[i].stat -= p.stat;
+                path = cgroupfs::parent_path(cgroup);
+            }
+            for path in cgroupfs::unit_paths(cgroup) {
+                let i = t.units.count_out(&self.units, path);
+                self.units[i].stat -= p.stat;
+            }
+        }
+        let i = t.users.count_out(&self.users, &p.uid);
+        self.users[i].stat -= p.stat;
+        let i = t.groups.count_out(&self.groups, &p.gid);
+        self.groups[i].stat -= p.stat;
+        if let Some(id) = &p.container {
+            let i = t.containers.count_out(&self.containers, id);
+            self.containers[i].stat -= p.stat;
+        }
+        if let Some(id) = &p.app {
+            let i = t.apps.count_out(&self.apps, id);
+            self.apps[i].stat -= p.stat;
+        }
+    }
+    /// Pushes, updates or removes the row of `tid` to match `all_threads`, as shown along with
+    /// its process.
+    fn apply_thread(&mut self, tid: u32, changes: &mut LevelChanges) {
+        let filtered = self
+            .all_threads
+            .get(&tid)
+            .filter(|t| self.tally.processes.contains(&t.parent_pid));
+        match (self.tally.threads.contains(&tid), filtered) {
+            (true, Some(t)) => {
+                let i = self.tally.threads.position(&self.threads, &tid);
+                let row = &mut self.threads[i];
+                if row == t {
+                    return;
+                }
+                changes.names |= row.name != t.name;
+                changes.tasks |= row.task != t.task || row.sched != t.sched;
+                row.clone_from(t);
+            }
+            (true, None) => {
+                self.tally.threads.count_out(&self.threads, &tid);
+            }
+            (false, Some(t)) => {
+                let i = self
+                    .tally
+                    .threads
+                    .count_in(&mut self.threads, &tid, || t.clone());
+                self.threads[i].clone_from(t);
+            }
+            (false, None) => return,
+        }
+        changes.stats = true;
+    }
+    /// Names each of `sids` after its leader while that is alive, and otherwise after its
+    /// first member, and lists the command lines of its members. Returns whether any was
+    /// renamed.
+    fn name_sessions(&mut self, sids: &BTreeSet<u32>) -> bool {
+        let mut renamed = false;
+        for &sid in sids {
+            if !self.tally.sessions.contains(&sid) {
+                continue;
+            }
+            let i = self.tally.sessions.position(&self.sessions, &sid);
+            let s = &mut self.sessions[i];
+            let name = s.name;
+            s.entries_cmdline.clear();
+            let members = self.session_members.range((sid, 0)..=(sid, u32::MAX));
+            for (n, &(_, pid)) in members.enumerate() {
+                let p = &self.all_processes[&pid];
+                if n == 0 {
+                    s.parent_lsid = p.parent_lsid;
+                }
+                if n == 0 || pid == sid {
+                    s.name = p.name;
+                }
+                if let Some(cmdline) = p.cmdline.as_ref() {
+                    if !s.entries_cmdline.is_empty() {
+                        s.entries_cmdline.push('\n');
+                    }
+                    s.entries_cmdline.push_str(cmdline);
+                }
+            }
+            renamed |= s.name != name;
+        }
+        renamed
+    }
+    /// Removes the rows left without processes by this update. Returns whether any process was.
+    fn remove_emptied_rows(&mut self) -> bool {
+        let t = &mut self.tally;
+        t.login_sessions.remove_emptied(&mut self.login_sessions);
+        t.sessions.remove_emptied(&mut self.sessions);
+        t.threads.remove_emptied(&mut self.threads);
+        t.cgroups.remove_emptied(&mut self.cgroups);
+        t.units.remove_emptied(&mut self.units);
+        t.users.remove_emptied(&mut self.users);
+        t.groups.remove_emptied(&mut self.groups);
+        t.containers.remove_emptied(&mut self.containers);
+        t.apps.remove_emptied(&mut self.apps);
+        t.processes.remove_emptied(&mut self.processes)
+    }
+    /// Drops the names of exited processes and threads from `strings`.
+    fn compact_strings(&mut self) {
+        self.affinity_strings.clear();
+        let processes = self
+            .all_processes
+            .values_mut()
+            .chain(&mut self.processes)
+            .flat_map(|p| [Some(&mut p.name), p.sched.affinity.as_mut()]);
+        let threads = self
+            .all_threads
+            .values_mut()
+            .chain(&mut self.threads)
+            .flat_map(|t| [Some(&mut t.name), t.sched.affinity.as_mut()]);
+        let sessions = self.sessions.iter_mut().map(|s| &mut s.name);
+        self.strings
+            .compact(processes.chain(threads).flatten().chain(sessions));
+    }
+    /// Assigns processes to applications by their cgroup, as systemd recommends that desktop
+    /// environments launch them, or else by their program being run by a `.desktop` file. The
+    /// processes of an application started without either, as from a terminal, inherit it from
+    /// their parent while in the same cgroup.
+    ///
+    /// Only the processes of `changes` are assigned, unless that changes what others inherit.
+    /// These are then added to `changes` as they were.
+    fn assign_apps(&mut self, changes: &mut Vec<(u32, Option<ProcessInfo>)>) {
+        let mut inherited_changed = false;
+        for (pid, old) in changes.iter() {
+            let Some(p) = self.all_processes.get(pid) else {
+                continue;
             };
-            self.login_sessions[idx].stat += s.stat;
+            let app = self.app_of(p);
+            inherited_changed |= old.as_ref().is_some_and(|old| old.app != app);
+            self.all_processes.get_mut(pid).unwrap().app = app;
+        }
+        if !inherited_changed {
+            return;
+        }
+        let changed_pids: HashSet<u32> = changes.iter().map(|&(pid, _)| pid).collect();
+        let reassigned: Vec<(u32, Option<String>)> = self
+            .all_processes
+            .values()
+            .filter(|p| !changed_pids.contains(&p.pid))
+            .filter_map(|p| {
+                let app = self.app_of(p);
+                (p.app != app).then_some((p.pid, app))
+            })
+            .collect();
+        for (pid, app) in reassigned {
+            let p = self.all_processes.get_mut(&pid).unwrap();
+            changes.push((pid, Some(p.clone())));
+            p.app = app;
+        }
+    }
+    fn app_of(&self, p: &ProcessInfo) -> Option<String> {
+        let entries = &self.desktop_entries;
+        let own_app = |p: &ProcessInfo| {
+            p.cgroup
+                .as_deref()
+                .and_then(|cgroup| entries.app_id_of_cgroup(cgroup))
+                .or_else(|| {
+                    let cmdline = p.cmdline.as_deref()?;
+                    entries.app_id_of_cmdline(cmdline).map(str::to_owned)
+                })
+        };
+        let mut ancestor = p;
+        // Bounded in case a racy read forms a cycle
+        for _ in 0..self.all_processes.len() {
+            if let Some(app) = own_app(ancestor) {
+                return Some(app);
+            }
+            match self.all_processes.get(&ancestor.task.ppid) {
+                Some(parent) if parent.cgroup == ancestor.cgroup => ancestor = parent,
+                _ => break,
+            }
         }
+        None
+    }
+    /// The passwd entry of `uid`, if any. Ids are looked up in the background once seen in
+    /// an update, and are `None` until their entry arrives.
+    pub fn user(&self, uid: u32) -> Option<&UserInfo> {
+        self.accounts.user(uid)
+    }
+    /// The group entry of `gid`, if any. Ids are looked up in the background once seen in
+    /// an update, and are `None` until their entry arrives.
+    pub fn group(&self, gid: u32) -> Option<&GroupInfo> {
+        self.accounts.group(gid)
+    }
+    /// The name of `uid`, or the number itself if it has no passwd entry or it is not yet known.
+    pub fn user_name(&self, uid: u32) -> String {
+        self.accounts.user_name(uid)
+    }
+    /// The name of `gid`, or the number itself if it has no group entry or it is not yet known.
+    pub fn group_name(&self, gid: u32) -> String {
+        self.accounts.group_name(gid)
+    }
+    pub fn get_filter(&self) -> &ProcFilter {
+        &self.filter
+    }
+    /// Only keeps the processes matching `filter`, along with their threads and the login
+    /// sessions, sessions and cgroups containing them. These then sum only matching processes.
+    pub fn filter(&mut self, filter: ProcFilter) {
+        if self.filter == filter {
+            return;
+        }
+        self.filter = filter;
+        self.apply_filter();
+    }
+    /// Makes every filtered level anew, as a change of filter can change any of their rows.
+    fn apply_filter(&mut self) {
+        self.tally = Tallies::default();
+        self.session_members.clear();
+        self.login_sessions.clear();
+        self.sessions.clear();
+        self.processes.clear();
+        self.threads.clear();
+        self.cgroups.clear();
+        self.units.clear();
+        self.users.clear();
+        self.groups.clear();
+        self.containers.clear();
+        self.apps.clear();
+        let mut changes = LevelChanges::default();
+        let pids: Vec<u32> = self.all_processes.keys().copied().collect();
+        for pid in pids {
+            let p = self.all_processes.remove(&pid).unwrap();
+            if self.filter.matches(self, &p) {
+                self.apply_process(None, Some(&p), &mut changes);
+            }
+            self.all_processes.insert(pid, p);
+        }
+        for t in self.all_threads.values() {
+            if self.tally.processes.contains(&t.parent_pid) {
+                self.tally
+                    .threads
+                    .count_in(&mut self.threads, &t.tid, || t.clone());
+            }
+        }
+        self.name_sessions(&changes.sessions);
+        self.tally.take_pushed();
         self.sort_self();
     }
+    fn login_session_name(&self, session: &LoginSessionIngest) -> String {
+        let systemd = session.systemd.as_ref();
+        let user = match systemd.and_then(|s| s.user.clone()) {
+            Some(user) => user,
+            None => match session.uid {
+                Some(uid) => self.user_name(uid),
+                None => "?".to_owned(),
+            },
+        };
+        let place: Vec<&str> = [
+            systemd.and_then(|s| s.seat.as_deref()),
+            systemd.and_then(|s| s.tty.as_deref()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        match place.is_empty() {
+            true => user,
+            false => format!("{user} ({})", place.join(", ")),
+        }
+    }
+    /// The pids of every process that `target` consists of, including those hidden by the filter.
+    pub fn pids_of(&self, target: &ProcTarget) -> Vec<u32> {
+        self.processes_of(target).map(|p| p.pid).collect()
+    }
+    /// The pid and start time since boot of every process that `target` consists of, which
+    /// unlike the pid alone can't come to refer to another process.
+    pub fn identities_of(&self, target: &ProcTarget) -> Vec<(u32, u64)> {
+        self.processes_of(target)
+            .map(|p| (p.pid, p.task.start_time_ms - self.boot_time * 1000))
+            .collect()
+    }
+    fn processes_of<'a>(
+        &'a self,
+        target: &'a ProcTarget,
+    ) -> impl Iterator<Item = &'a ProcessInfo> + 'a {
+        if let ProcTarget::Process(pid) = target {
+            return Either::Left(self.all_processes.get(pid).into_iter());
+        }
+        Either::Right(self.all_processes.values().filter(move |p| match target {
+            ProcTarget::LoginSession(lsid) => p.parent_lsid == *lsid,
+            ProcTarget::Session(sid) => p.parent_sid == *sid,
+            ProcTarget::Process(_) => unreachable!(),
+            ProcTarget::Cgroup(path) | ProcTarget::Unit(path) => {
+                p.cgroup.as_deref().is_some_and(|cgroup| {
+                    cgroup == path
+                        || path == "/"
+                        || cgroup
+                            .strip_prefix(path.as_str())
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+            }
+            ProcTarget::User(uid) => p.uid == *uid,
+            ProcTarget::Group(gid) => p.gid == *gid,
+            ProcTarget::Container(id) => p.container.as_ref() == Some(id),
+            ProcTarget::App(id) => p.app.as_ref() == Some(id),
+        }))
+    }
+    /// The tids of every thread of every process that `target` consists of, including those
+    /// hidden by the filter.
+    pub fn tids_of(&self, target: &ProcTarget) -> Vec<u32> {
+        // In order, as `all_processes` is
+        let pids = self.pids_of(target);
+        self.all_threads
+            .values()
+            .filter(|t| pids.binary_search(&t.parent_pid).is_ok())
+            .map(|t| t.tid)
+            .collect()
+    }
+    /// A short human-readable description, such as `process 1234 (bash)`.
+    pub fn describe(&self, target: &ProcTarget) -> String {
+        match target {
+            ProcTarget::LoginSession(lsid) => {
+                match self.login_sessions.iter().find(|ls| ls.lsid == *lsid) {
+                    Some(ls) => format!("login session {}", ls.name),
+                    None => format!("login session {lsid:?}"),
+                }
+            }
+            ProcTarget::Session(sid) => match self.sessions.iter().find(|s| s.sid == *sid) {
+                Some(s) => format!("session {sid} ({})", self.strings.get(s.name)),
+                None => format!("session {sid}"),
+            },
+            ProcTarget::Process(pid) => match self.processes.iter().find(|p| p.pid == *pid) {
+                Some(p) => format!("process {pid} ({})", self.strings.get(p.name)),
+                None => format!("process {pid}"),
+            },
+            ProcTarget::Cgroup(path) => format!("cgroup {path}"),
+            ProcTarget::Unit(path) => format!("unit {}", cgroupfs::unit_name(path)),
+            ProcTarget::User(uid) => format!("user {}", self.user_name(*uid)),
+            ProcTarget::Group(gid) => format!("group {}", self.group_name(*gid)),
+            ProcTarget::Container(id) => match self.containers.iter().find(|c| c.id == *id) {
+                Some(ContainerInfo {
+                    hostname: Some(hostname),
+                    ..
+                }) => format!("{} container {} ({hostname})", id.runtime, id.short_id()),
+                _ => format!("{} container {}", id.runtime, id.short_id()),
+            },
+            ProcTarget::App(id) => match self.apps.iter().find(|app| app.id == *id) {
+                Some(app) => format!("app {}", app.name),
+                None => format!("app {id}"),
+            },
+        }
+    }
     pub fn get_sort_by(&self) -> ProcSortBy {
         self.sort_by
     }
@@ -206,90 +1247,633 @@ impl ProcInfo {
         self.sort_self();
     }
     fn sort_self(&mut self) {
+        self.sort_levels();
+        self.build_process_tree();
+    }
+    fn build_process_tree(&mut self) {
+        let pid_to_idx: HashMap<u32, usize> = self
+            .processes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.pid, i))
+            .collect();
+        let mut children = vec![Vec::new(); self.processes.len()];
+        let mut roots = Vec::new();
+        for (i, p) in self.processes.iter().enumerate() {
+            match pid_to_idx.get(&p.task.ppid) {
+                Some(&parent) if parent != i => children[parent].push(i),
+                _ => roots.push(i),
+            }
+        }
+        let mut visited = vec![false; self.processes.len()];
+        self.process_tree = Vec::with_capacity(self.processes.len());
+        self.tree_parents = Vec::with_capacity(self.processes.len());
+        // A racy read could in theory form a cycle without any root, so also start from any
+        // process not yet visited.
+        for root in roots.into_iter().chain(0..self.processes.len()) {
+            if !visited[root] {
+                push_subtree(
+                    &mut self.process_tree,
+                    &mut self.tree_parents,
+                    &mut visited,
+                    &self.processes,
+                    &children,
+                    root,
+                    None,
+                );
+            }
+        }
+        self.tree_entries = vec![0; self.processes.len()];
+        for (i, entry) in self.process_tree.iter().enumerate() {
+            self.tree_entries[entry.idx] = i;
+        }
+
+        fn push_subtree(
+            tree: &mut Vec<ProcessTreeEntry>,
+            parents: &mut Vec<Option<usize>>,
+            visited: &mut [bool],
+            processes: &[ProcessInfo],
+            children: &[Vec<usize>],
+            idx: usize,
+            parent: Option<usize>,
+        ) -> ProcStat {
+            visited[idx] = true;
+            let entry_idx = tree.len();
+            tree.push(ProcessTreeEntry {
+                idx,
+                depth: parent.map_or(0, |parent| tree[parent].depth + 1),
+                num_descendants: 0,
+                subtree_stat: ProcStat::ZERO,
+            });
+            parents.push(parent);
+            let mut stat = processes[idx].stat;
+            for &child in &children[idx] {
+                if !visited[child] {
+                    stat += push_subtree(
+                        tree,
+                        parents,
+                        visited,
+                        processes,
+                        children,
+                        child,
+                        Some(entry_idx),
+                    );
+                }
+            }
+            tree[entry_idx].num_descendants = tree.len() - entry_idx - 1;
+            tree[entry_idx].subtree_stat = stat;
+            stat
+        }
+    }
+    /// Updates the subtree stats of `process_tree` by the changed stats of processes, when the
+    /// tree itself is unchanged.
+    fn update_subtree_stats(&mut self, process_stats: &[(u32, ProcStat, ProcStat)]) {
+        for &(pid, old, new) in process_stats {
+            let idx = self.tally.processes.position(&self.processes, &pid);
+            let mut entry = Some(self.tree_entries[idx]);
+            while let Some(i) = entry {
+                let stat = &mut self.process_tree[i].subtree_stat;
+                *stat = *stat - old + new;
+                entry = self.tree_parents[i];
+            }
+        }
+    }
+    /// Sorts every level that is out of order. Returns whether processes were.
+    fn sort_levels(&mut self) -> bool {
         match self.sort_by {
             ProcSortBy::Id => {
-                self.login_sessions.sort_by_key(|ls| ls.lsid);
-                self.sessions.sort_by_key(|s| s.sid);
-                self.processes.sort_by_key(|p| p.pid);
-                self.threads.sort_by_key(|t| t.tid);
+                sort_rows_by_key(&mut self.login_sessions, |ls| ls.lsid);
+                sort_rows_by_key(&mut self.sessions, |s| s.sid);
+                sort_rows_by_key(&mut self.threads, |t| t.tid);
+                self.sort_cgroups_as_tree();
+                sort_units_as_tree(&mut self.units, |_| ());
+                sort_rows_by_key(&mut self.users, |u| u.uid);
+                sort_rows_by_key(&mut self.groups, |g| g.gid);
+                sort_rows(&mut self.containers, |c1, c2| Ord::cmp(&c1.id, &c2.id));
+                sort_rows(&mut self.apps, |a1, a2| Ord::cmp(&a1.id, &a2.id));
+                sort_rows_by_key(&mut self.processes, |p| p.pid)
             }
             ProcSortBy::Name => {
-                self.login_sessions.sort_by_key(|ls| ls.lsid);
-                self.sessions.sort_by(|s1, s2| {
+                sort_rows_by_key(&mut self.login_sessions, |ls| ls.lsid);
+                sort_rows(&mut self.sessions, |s1, s2| {
                     Ord::cmp(
                         &(&self.strings.get(s1.name), s1.sid),
                         &(&self.strings.get(s2.name), s2.sid),
                     )
                 });
-                self.processes.sort_by(|p1, p2| {
-                    Ord::cmp(
-                        &(&self.strings.get(p1.name), p1.pid),
-                        &(&self.strings.get(p2.name), p2.pid),
-                    )
-                });
-                self.threads.sort_by(|t1, t2| {
+                sort_rows(&mut self.threads, |t1, t2| {
                     Ord::cmp(
                         &(&self.strings.get(t1.name), t1.tid),
                         &(&self.strings.get(t2.name), t2.tid),
                     )
                 });
-            }
-            ProcSortBy::Cpu => {
-                self.login_sessions.sort_by_key(|ls| {
-                    (
-                        u32::MAX - ls.stat.user_time_millis - ls.stat.system_time_millis,
-                        ls.lsid,
-                    )
+                self.sort_cgroups_as_tree();
+                sort_units_as_tree(&mut self.units, |u| u.name.clone());
+                sort_rows(&mut self.users, |u1, u2| {
+                    Ord::cmp(&(&u1.name, u1.uid), &(&u2.name, u2.uid))
                 });
-                self.sessions.sort_by_key(|s| {
-                    (
-                        u32::MAX - s.stat.user_time_millis - s.stat.system_time_millis,
-                        s.sid,
-                    )
+                sort_rows(&mut self.groups, |g1, g2| {
+                    Ord::cmp(&(&g1.name, g1.gid), &(&g2.name, g2.gid))
                 });
-                self.processes.sort_by_key(|p| {
-                    (
-                        u32::MAX - p.stat.user_time_millis - p.stat.system_time_millis,
-                        p.pid,
+                // Those without a hostname last
+                sort_rows(&mut self.containers, |c1, c2| {
+                    Ord::cmp(
+                        &(c1.hostname.is_none(), &c1.hostname, &c1.id),
+                        &(c2.hostname.is_none(), &c2.hostname, &c2.id),
                     )
                 });
-                self.threads.sort_by_key(|t| {
-                    (
-                        u32::MAX - t.stat.user_time_millis - t.stat.system_time_millis,
-                        t.tid,
-                    )
+                sort_rows(&mut self.apps, |a1, a2| {
+                    Ord::cmp(&(&a1.name, &a1.id), &(&a2.name, &a2.id))
                 });
+                sort_rows(&mut self.processes, |p1, p2| {
+                    Ord::cmp(
+                        &(&self.strings.get(p1.name), p1.pid),
+                        &(&self.strings.get(p2.name), p2.pid),
+                    )
+                })
             }
+            ProcSortBy::Cpu => {
+                self.sort_by_stat(|stat| Reverse(stat.user_time_millis + stat.system_time_millis))
+            }
+            ProcSortBy::ChildrenCpu => self.sort_by_stat(|stat| Reverse(stat.children_time_millis)),
             ProcSortBy::DiskRead => {
-                self.login_sessions
-                    .sort_by_key(|ls| (u64::MAX - ls.stat.disk_read_bytes_per_second, ls.lsid));
-                self.sessions
-                    .sort_by_key(|s| (u64::MAX - s.stat.disk_read_bytes_per_second, s.sid));
-                self.processes
-                    .sort_by_key(|p| (u64::MAX - p.stat.disk_read_bytes_per_second, p.pid));
-                self.threads
-                    .sort_by_key(|t| (u64::MAX - t.stat.disk_read_bytes_per_second, t.tid));
+                self.sort_by_stat(|stat| Reverse(stat.disk_read_bytes_per_second))
             }
             ProcSortBy::DiskWrite => {
-                self.login_sessions
-                    .sort_by_key(|ls| (u64::MAX - ls.stat.disk_write_bytes_per_second, ls.lsid));
-                self.sessions
-                    .sort_by_key(|s| (u64::MAX - s.stat.disk_write_bytes_per_second, s.sid));
-                self.processes
-                    .sort_by_key(|p| (u64::MAX - p.stat.disk_write_bytes_per_second, p.pid));
-                self.threads
-                    .sort_by_key(|t| (u64::MAX - t.stat.disk_write_bytes_per_second, t.tid));
+                self.sort_by_stat(|stat| Reverse(stat.disk_write_bytes_per_second))
+            }
+            ProcSortBy::NetSend => {
+                self.sort_by_stat(|stat| Reverse(stat.net_send_bytes_per_second))
             }
-            ProcSortBy::Memory => {
-                self.login_sessions
-                    .sort_by_key(|ls| (u64::MAX - ls.stat.mem_bytes, ls.lsid));
-                self.sessions
-                    .sort_by_key(|s| (u64::MAX - s.stat.mem_bytes, s.sid));
-                self.processes
-                    .sort_by_key(|p| (u64::MAX - p.stat.mem_bytes, p.pid));
-                self.threads
-                    .sort_by_key(|t| (u64::MAX - t.stat.mem_bytes, t.tid));
+            ProcSortBy::NetReceive => {
+                self.sort_by_stat(|stat| Reverse(stat.net_receive_bytes_per_second))
             }
+            ProcSortBy::Memory => self.sort_by_stat(|stat| Reverse(stat.mem_bytes)),
+            ProcSortBy::Pss => self.sort_by_stat(|stat| Reverse(stat.pss_bytes)),
+            ProcSortBy::Uss => self.sort_by_stat(|stat| Reverse(stat.uss_bytes)),
+            ProcSortBy::RssAnon => self.sort_by_stat(|stat| Reverse(stat.rss_anon_bytes)),
+            ProcSortBy::RssFile => self.sort_by_stat(|stat| Reverse(stat.rss_file_bytes)),
+            ProcSortBy::RssShmem => self.sort_by_stat(|stat| Reverse(stat.rss_shmem_bytes)),
+            ProcSortBy::Swap => self.sort_by_stat(|stat| Reverse(stat.swap_bytes)),
+            ProcSortBy::MinorFaults => {
+                self.sort_by_stat(|stat| Reverse(stat.minor_faults_per_second))
+            }
+            ProcSortBy::MajorFaults => {
+                self.sort_by_stat(|stat| Reverse(stat.major_faults_per_second))
+            }
+            ProcSortBy::VoluntarySwitches => {
+                self.sort_by_stat(|stat| Reverse(stat.voluntary_switches_per_second))
+            }
+            ProcSortBy::InvoluntarySwitches => {
+                self.sort_by_stat(|stat| Reverse(stat.involuntary_switches_per_second))
+            }
+            ProcSortBy::RunDelay => self.sort_by_stat(|stat| Reverse(stat.run_delay_millis)),
+            ProcSortBy::Gpu => self.sort_by_stat(|stat| Reverse(stat.gpu_time_millis)),
+            ProcSortBy::Vram => self.sort_by_stat(|stat| Reverse(stat.gpu_vram_bytes)),
+            ProcSortBy::CgroupCpu => self.sort_by_cgroup_stat(|stat| {
+                Reverse(stat.user_time_millis + stat.system_time_millis)
+            }),
+            ProcSortBy::CgroupMemory => self.sort_by_cgroup_stat(|stat| Reverse(stat.mem_bytes)),
+            ProcSortBy::CgroupRead => {
+                self.sort_by_cgroup_stat(|stat| Reverse(stat.io_read_bytes_per_second))
+            }
+            ProcSortBy::CgroupWrite => {
+                self.sort_by_cgroup_stat(|stat| Reverse(stat.io_write_bytes_per_second))
+            }
+            ProcSortBy::CgroupPids => self.sort_by_cgroup_stat(|stat| Reverse(stat.pids)),
+            ProcSortBy::State => self.sort_by_task(|task, _| task.state),
+            ProcSortBy::StartTime => self.sort_by_task(|task, _| Reverse(task.start_time_ms)),
+            ProcSortBy::Elapsed => self.sort_by_task(|task, _| task.start_time_ms),
+            // Terminals first
+            ProcSortBy::Tty => self.sort_by_task(|task, _| (task.tty_nr == 0, task.tty_nr)),
+            ProcSortBy::Ppid => self.sort_by_task(|task, _| task.ppid),
+            ProcSortBy::Nice => self.sort_by_task(|_, sched| sched.nice),
+            ProcSortBy::Threads => self.sort_by_task(|task, _| Reverse(task.num_threads)),
+        }
+    }
+    /// Sorts processes and threads by `key`, breaking ties by id, and the other levels by id.
+    /// Returns whether processes were out of order.
+    fn sort_by_task<K: Ord>(&mut self, key: impl Fn(&TaskInfo, &SchedInfo) -> K) -> bool {
+        sort_rows_by_key(&mut self.login_sessions, |ls| ls.lsid);
+        sort_rows_by_key(&mut self.sessions, |s| s.sid);
+        sort_rows(&mut self.threads, |a, b| {
+            Ord::cmp(
+                &(key(&a.task, &a.sched), a.tid),
+                &(key(&b.task, &b.sched), b.tid),
+            )
+        });
+        self.sort_cgroups_as_tree();
+        sort_units_as_tree(&mut self.units, |_| ());
+        sort_rows_by_key(&mut self.users, |u| u.uid);
+        sort_rows_by_key(&mut self.groups, |g| g.gid);
+        sort_rows(&mut self.containers, |a, b| Ord::cmp(&a.id, &b.id));
+        sort_rows(&mut self.apps, |a, b| Ord::cmp(&a.id, &b.id));
+        sort_rows(&mut self.processes, |a, b| {
+            Ord::cmp(
+                &(key(&a.task, &a.sched), a.pid),
+                &(key(&b.task, &b.sched), b.pid),
+            )
+        })
+    }
+    /// Sorts cgroups by `key`, breaking ties by path, and the other levels by id. Returns
+    /// whether processes were out of order.
+    fn sort_by_cgroup_stat<K: Ord>(&mut self, key: impl Fn(&CgroupStat) -> K) -> bool {
+        sort_rows_by_key(&mut self.login_sessions, |ls| ls.lsid);
+        sort_rows_by_key(&mut self.sessions, |s| s.sid);
+        sort_rows_by_key(&mut self.threads, |t| t.tid);
+        sort_rows(&mut self.cgroups, |a, b| {
+            Ord::cmp(&key(&a.kernel_stat), &key(&b.kernel_stat))
+                .then_with(|| Ord::cmp(&a.path, &b.path))
+        });
+        sort_units_as_tree(&mut self.units, |_| ());
+        sort_rows_by_key(&mut self.users, |u| u.uid);
+        sort_rows_by_key(&mut self.groups, |g| g.gid);
+        sort_rows(&mut self.containers, |a, b| Ord::cmp(&a.id, &b.id));
+        sort_rows(&mut self.apps, |a, b| Ord::cmp(&a.id, &b.id));
+        sort_rows_by_key(&mut self.processes, |p| p.pid)
+    }
+    /// Sorts every level by `key`, breaking ties by id. Returns whether processes were out of
+    /// order.
+    fn sort_by_stat<K: Ord>(&mut self, key: impl Fn(&ProcStat) -> K) -> bool {
+        sort_rows(&mut self.login_sessions, |a, b| {
+            Ord::cmp(&(key(&a.stat), a.lsid), &(key(&b.stat), b.lsid))
+        });
+        sort_rows(&mut self.sessions, |a, b| {
+            Ord::cmp(&(key(&a.stat), a.sid), &(key(&b.stat), b.sid))
+        });
+        sort_rows(&mut self.threads, |a, b| {
+            Ord::cmp(&(key(&a.stat), a.tid), &(key(&b.stat), b.tid))
+        });
+        sort_rows(&mut self.cgroups, |a, b| {
+            Ord::cmp(&key(&a.stat), &key(&b.stat)).then_with(|| Ord::cmp(&a.path, &b.path))
+        });
+        sort_units_as_tree(&mut self.units, |u| key(&u.stat));
+        sort_rows(&mut self.users, |a, b| {
+            Ord::cmp(&(key(&a.stat), a.uid), &(key(&b.stat), b.uid))
+        });
+        sort_rows(&mut self.groups, |a, b| {
+            Ord::cmp(&(key(&a.stat), a.gid), &(key(&b.stat), b.gid))
+        });
+        sort_rows(&mut self.containers, |a, b| {
+            Ord::cmp(&key(&a.stat), &key(&b.stat)).then_with(|| Ord::cmp(&a.id, &b.id))
+        });
+        sort_rows(&mut self.apps, |a, b| {
+            Ord::cmp(&key(&a.stat), &key(&b.stat)).then_with(|| Ord::cmp(&a.id, &b.id))
+        });
+        sort_rows(&mut self.processes, |a, b| {
+            Ord::cmp(&(key(&a.stat), a.pid), &(key(&b.stat), b.pid))
+        })
+    }
+    /// Sorts cgroups depth-first, so that every cgroup directly follows its parent.
+    fn sort_cgroups_as_tree(&mut self) {
+        sort_rows(&mut self.cgroups, |a, b| {
+            Iterator::cmp(a.path.split('/'), b.path.split('/'))
+        });
+    }
+}
+/// Sorts `rows` by `cmp` unless they already are, which is quicker to check than to sort.
+/// Returns whether they were out of order.
+fn sort_rows<T>(rows: &mut [T], cmp: impl Fn(&T, &T) -> Ordering) -> bool {
+    if rows.is_sorted_by(|a, b| cmp(a, b).is_le()) {
+        return false;
+    }
+    rows.sort_by(cmp);
+    true
+}
+fn sort_rows_by_key<T, K: Ord>(rows: &mut [T], key: impl Fn(&T) -> K) -> bool {
+    sort_rows(rows, |a, b| Ord::cmp(&key(a), &key(b)))
+}
+/// Sorts units depth-first, with siblings sorted by `key` and then by path.
+fn sort_units_as_tree<K: Ord>(units: &mut Vec<UnitInfo>, key: impl Fn(&UnitInfo) -> K) {
+    let key_by_path: HashMap<&str, K> = units.iter().map(|u| (u.path.as_str(), key(u))).collect();
+    // The keys of every ancestor, outermost first
+    let chains: Vec<Vec<(&K, &str)>> = units
+        .iter()
+        .map(|u| {
+            let mut chain: Vec<(&K, &str)> = cgroupfs::unit_paths(&u.path)
+                .map(|path| (&key_by_path[path], path))
+                .collect();
+            chain.reverse();
+            chain
+        })
+        .collect();
+    if chains.is_sorted() {
+        return;
+    }
+    let mut order: Vec<usize> = (0..units.len()).collect();
+    order.sort_by(|&a, &b| Ord::cmp(&chains[a], &chains[b]));
+    let mut taken: Vec<Option<UnitInfo>> = units.drain(..).map(Some).collect();
+    *units = order
+        .into_iter()
+        .map(|i| taken[i].take().unwrap())
+        .collect();
+}
+impl<K: Hash + Eq> Tally<K> {
+    /// Whether the row of `key` has any process.
+    fn contains<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.rows.get(key).is_some_and(|&(_, count)| count > 0)
+    }
+    /// The position of the row of `key` in `rows`, which must hold one.
+    fn position<T: Row<Key = Q>, Q: ?Sized + Hash + Eq>(&mut self, rows: &[T], key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.rows[key].0;
+        if rows.get(i).is_some_and(|row| row.key() == key) {
+            return i;
+        }
+        // Moved by sorting
+        for (i, row) in rows.iter().enumerate() {
+            self.rows.get_mut(row.key()).unwrap().0 = i;
+        }
+        self.rows[key].0
+    }
+    /// Counts a process in the row of `key`, pushing `new_row` if there is none. Returns its
+    /// position.
+    fn count_in<T: Row<Key = Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(
+        &mut self,
+        rows: &mut Vec<T>,
+        key: &Q,
+        new_row: impl FnOnce() -> T,
+    ) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        if self.rows.contains_key(key) {
+            let i = self.position(rows, key);
+            self.rows.get_mut(key).unwrap().1 += 1;
+            return i;
+        }
+        rows.push(new_row());
+        self.rows.insert(key.to_owned(), (rows.len() - 1, 1));
+        self.pushed = true;
+        rows.len() - 1
+    }
+    /// Undoes `count_in`. Returns the position of the row.
+    fn count_out<T: Row<Key = Q>, Q: ?Sized + Hash + Eq>(&mut self, rows: &[T], key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.position(rows, key);
+        let count = &mut self.rows.get_mut(key).unwrap().1;
+        *count -= 1;
+        if *count == 0 {
+            self.emptied.push(i);
+        }
+        i
+    }
+    /// Removes the rows still without processes. Returns whether there were any.
+    fn remove_emptied<T: Row>(&mut self, rows: &mut Vec<T>) -> bool
+    where
+        K: Borrow<T::Key>,
+    {
+        let emptied = mem::take(&mut self.emptied);
+        let remove = emptied
+            .iter()
+            .any(|&i| rows.get(i).is_some_and(|row| !self.contains(row.key())));
+        if remove {
+            rows.retain(|row| self.contains(row.key()));
+            self.rows.retain(|_, &mut (_, count)| count > 0);
+        }
+        remove
+    }
+}
+impl<K> Default for Tally<K> {
+    fn default() -> Self {
+        Self {
+            rows: HashMap::new(),
+            emptied: Vec::new(),
+            pushed: false,
+        }
+    }
+}
+impl Tallies {
+    /// Whether a row was pushed to any level since last called.
+    fn take_pushed(&mut self) -> bool {
+        let pushed = [
+            &mut self.login_sessions.pushed,
+            &mut self.sessions.pushed,
+            &mut self.processes.pushed,
+            &mut self.threads.pushed,
+            &mut self.cgroups.pushed,
+            &mut self.units.pushed,
+            &mut self.users.pushed,
+            &mut self.groups.pushed,
+            &mut self.containers.pushed,
+            &mut self.apps.pushed,
+        ];
+        pushed.into_iter().fold(false, |any, p| mem::take(p) | any)
+    }
+}
+impl Row for LoginSessionInfo {
+    type Key = Lsid;
+    fn key(&self) -> &Lsid {
+        &self.lsid
+    }
+}
+impl Row for SessionInfo {
+    type Key = u32;
+    fn key(&self) -> &u32 {
+        &self.sid
+    }
+}
+impl Row for ProcessInfo {
+    type Key = u32;
+    fn key(&self) -> &u32 {
+        &self.pid
+    }
+}
+impl Row for ThreadInfo {
+    type Key = u32;
+    fn key(&self) -> &u32 {
+        &self.tid
+    }
+}
+impl Row for CgroupInfo {
+    type Key = str;
+    fn key(&self) -> &str {
+        &self.path
+    }
+}
+impl Row for UnitInfo {
+    type Key = str;
+    fn key(&self) -> &str {
+        &self.path
+    }
+}
+impl Row for UserUsage {
+    type Key = u32;
+    fn key(&self) -> &u32 {
+        &self.uid
+    }
+}
+impl Row for GroupUsage {
+    type Key = u32;
+    fn key(&self) -> &u32 {
+        &self.gid
+    }
+}
+impl Row for ContainerInfo {
+    type Key = ContainerId;
+    fn key(&self) -> &ContainerId {
+        &self.id
+    }
+}
+impl Row for AppInfo {
+    type Key = str;
+    fn key(&self) -> &str {
+        &self.id
+    }
+}
+// Unlike derived ones, `clone_from` reuses the strings of `self`, as rows are updated in place
+// with it whenever their process changes
+impl Clone for SessionInfo {
+    fn clone(&self) -> Self {
+        Self {
+            entries_cmdline: self.entries_cmdline.clone(),
+            ..*self
+        }
+    }
+    fn clone_from(&mut self, source: &Self) {
+        self.parent_lsid = source.parent_lsid;
+        self.sid = source.sid;
+        self.name = source.name;
+        self.entries_cmdline.clone_from(&source.entries_cmdline);
+        self.stat = source.stat;
+    }
+}
+impl Clone for ProcessInfo {
+    fn clone(&self) -> Self {
+        Self {
+            cmdline: self.cmdline.clone(),
+            cgroup: self.cgroup.clone(),
+            unit: self.unit.clone(),
+            container: self.container.clone(),
+            app: self.app.clone(),
+            ..*self
+        }
+    }
+    fn clone_from(&mut self, source: &Self) {
+        self.parent_lsid = source.parent_lsid;
+        self.parent_sid = source.parent_sid;
+        self.pid = source.pid;
+        self.uid = source.uid;
+        self.gid = source.gid;
+        self.name = source.name;
+        self.cmdline.clone_from(&source.cmdline);
+        self.cgroup.clone_from(&source.cgroup);
+        self.unit.clone_from(&source.unit);
+        if self.container != source.container {
+            self.container.clone_from(&source.container);
+        }
+        self.ns_pid = source.ns_pid;
+        self.app.clone_from(&source.app);
+        self.task = source.task;
+        self.sched = source.sched;
+        self.stat = source.stat;
+    }
+}
+impl TaskInfo {
+    /// Like `pts/3` or `tty1`, or `None` without a controlling terminal.
+    pub fn tty_name(&self) -> Option<String> {
+        if self.tty_nr == 0 {
+            return None;
+        }
+        let major = (self.tty_nr >> 8) & 0xfff;
+        let minor = (self.tty_nr & 0xff) | ((self.tty_nr >> 12) & 0xfff00);
+        Some(match major {
+            4 if minor < 64 => format!("tty{minor}"),
+            4 => format!("ttyS{}", minor - 64),
+            136..=143 => format!("pts/{}", (major - 136) * 256 + minor),
+            _ => format!("{major}:{minor}"),
+        })
+    }
+    pub fn format_start_time(&self) -> String {
+        format_time(self.start_time_ms)
+    }
+    pub fn elapsed_seconds(&self) -> u64 {
+        unix_time_ms().saturating_sub(self.start_time_ms) / 1000
+    }
+    pub fn format_elapsed(&self) -> String {
+        format_duration(self.elapsed_seconds())
+    }
+}
+impl ExitedProcessInfo {
+    pub fn format_start_time(&self) -> String {
+        format_time(self.start_time_ms)
+    }
+    pub fn format_end_time(&self) -> String {
+        format_time(self.end_time_ms)
+    }
+    pub fn format_lifetime(&self) -> String {
+        format_duration(self.end_time_ms.saturating_sub(self.start_time_ms) / 1000)
+    }
+}
+/// In local time, like `ps`: `14:05` within the last day, `Mar 14` within the year and
+/// otherwise just the year.
+fn format_time(time_ms: u64) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let time = local_time(time_ms / 1000);
+    let now_ms = unix_time_ms();
+    if now_ms.saturating_sub(time_ms) < 24 * 3600 * 1000 {
+        format!("{:02}:{:02}", time.tm_hour, time.tm_min)
+    } else if local_time(now_ms / 1000).tm_year == time.tm_year {
+        format!("{} {:2}", MONTHS[time.tm_mon as usize], time.tm_mday)
+    } else {
+        (time.tm_year + 1900).to_string()
+    }
+}
+/// Like `ps`: `[[days-]hours:]minutes:seconds`.
+fn format_duration(seconds: u64) -> String {
+    let (days, hours, mins, secs) = (
+        seconds / 86400,
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60,
+    );
+    match (days, hours) {
+        (0, 0) => format!("{mins:02}:{secs:02}"),
+        (0, _) => format!("{hours:02}:{mins:02}:{secs:02}"),
+        _ => format!("{days}-{hours:02}:{mins:02}:{secs:02}"),
+    }
+}
+fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+fn local_time(unix_seconds: u64) -> libc::tm {
+    let time = unix_seconds as libc::time_t;
+    let mut tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&time, &mut tm) };
+    tm
+}
+impl UnitInfo {
+    /// Without any process yet.
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            name: cgroupfs::unit_name(path),
+            depth: cgroupfs::unit_paths(path).count() - 1,
+            stat: ProcStat::ZERO,
+        }
+    }
+}
+impl CgroupStat {
+    fn new(cgroup: &CgroupIngest, update_hz: u8) -> Self {
+        Self {
+            user_time_millis: (cgroup.user_time_us / 1000) as u32,
+            system_time_millis: (cgroup.system_time_us / 1000) as u32,
+            io_read_bytes_per_second: cgroup.read_bytes / update_hz as u64,
+            io_write_bytes_per_second: cgroup.write_bytes / update_hz as u64,
+            mem_bytes: cgroup.memory_bytes,
+            pids: cgroup.pids,
         }
     }
 }
@@ -298,10 +1882,73 @@ impl ProcStat {
         guest_time_millis: 0,
         user_time_millis: 0,
         system_time_millis: 0,
+        children_time_millis: 0,
         disk_read_bytes_per_second: 0,
         disk_write_bytes_per_second: 0,
+        net_send_bytes_per_second: 0,
+        net_receive_bytes_per_second: 0,
+        minor_faults_per_second: 0,
+        major_faults_per_second: 0,
+        voluntary_switches_per_second: 0,
+        involuntary_switches_per_second: 0,
+        run_delay_millis: 0,
+        gpu_time_millis: 0,
         mem_bytes: 0,
+        pss_bytes: 0,
+        uss_bytes: 0,
+        rss_anon_bytes: 0,
+        rss_file_bytes: 0,
+        rss_shmem_bytes: 0,
+        swap_bytes: 0,
+        gpu_vram_bytes: 0,
     };
+    fn memory_of(process: &ProcessIngest) -> Self {
+        Self {
+            mem_bytes: process.mem_kb.vm_rss * 1024,
+            pss_bytes: process.pss_kb * 1024,
+            uss_bytes: process.uss_kb * 1024,
+            rss_anon_bytes: process.mem_kb.rss_anon * 1024,
+            rss_file_bytes: process.mem_kb.rss_file * 1024,
+            rss_shmem_bytes: process.mem_kb.rss_shmem * 1024,
+            swap_bytes: process.mem_kb.vm_swap * 1024,
+            gpu_vram_bytes: process.gpu_vram_bytes,
+            ..Self::ZERO
+        }
+    }
+    /// Reconciles the sum over live threads with the totals of the process, which also include
+    /// threads that exited since last update. As the totals are read at a slightly different
+    /// time, this never reports less than the live threads.
+    fn with_process_times_of(self, process: &ProcessIngest) -> Self {
+        Self {
+            guest_time_millis: self.guest_time_millis.max(process.guest_time_ms),
+            user_time_millis: self.user_time_millis.max(process.user_time_ms),
+            system_time_millis: self.system_time_millis.max(process.system_time_ms),
+            children_time_millis: process.children_time_ms,
+            ..self
+        }
+    }
+    /// GPU and network usage, which the kernel accounts per open file rather than per thread.
+    fn with_open_files_of(self, process: &ProcessIngest, update_hz: u8) -> Self {
+        Self {
+            gpu_time_millis: (process.gpu_time_ns / 1_000_000) as u32,
+            net_send_bytes_per_second: process.net_send_bytes / update_hz as u64,
+            net_receive_bytes_per_second: process.net_receive_bytes / update_hz as u64,
+            ..self
+        }
+    }
+    fn with_memory_of(self, mem: Self) -> Self {
+        Self {
+            mem_bytes: mem.mem_bytes,
+            pss_bytes: mem.pss_bytes,
+            uss_bytes: mem.uss_bytes,
+            rss_anon_bytes: mem.rss_anon_bytes,
+            rss_file_bytes: mem.rss_file_bytes,
+            rss_shmem_bytes: mem.rss_shmem_bytes,
+            swap_bytes: mem.swap_bytes,
+            gpu_vram_bytes: mem.gpu_vram_bytes,
+            ..self
+        }
+    }
 }
 impl Add for ProcStat {
     type Output = Self;
@@ -310,11 +1957,31 @@ impl Add for ProcStat {
             guest_time_millis: self.guest_time_millis + rhs.guest_time_millis,
             user_time_millis: self.user_time_millis + rhs.user_time_millis,
             system_time_millis: self.system_time_millis + rhs.system_time_millis,
+            children_time_millis: self.children_time_millis + rhs.children_time_millis,
             disk_read_bytes_per_second: self.disk_read_bytes_per_second
                 + rhs.disk_read_bytes_per_second,
             disk_write_bytes_per_second: self.disk_write_bytes_per_second
                 + rhs.disk_write_bytes_per_second,
+            net_send_bytes_per_second: self.net_send_bytes_per_second
+                + rhs.net_send_bytes_per_second,
+            net_receive_bytes_per_second: self.net_receive_bytes_per_second
+                + rhs.net_receive_bytes_per_second,
+            minor_faults_per_second: self.minor_faults_per_second + rhs.minor_faults_per_second,
+            major_faults_per_second: self.major_faults_per_second + rhs.major_faults_per_second,
+            voluntary_switches_per_second: self.voluntary_switches_per_second
+                + rhs.voluntary_switches_per_second,
+            involuntary_switches_per_second: self.involuntary_switches_per_second
+                + rhs.involuntary_switches_per_second,
+            run_delay_millis: self.run_delay_millis + rhs.run_delay_millis,
+            gpu_time_millis: self.gpu_time_millis + rhs.gpu_time_millis,
             mem_bytes: self.mem_bytes + rhs.mem_bytes,
+            pss_bytes: self.pss_bytes + rhs.pss_bytes,
+            uss_bytes: self.uss_bytes + rhs.uss_bytes,
+            rss_anon_bytes: self.rss_anon_bytes + rhs.rss_anon_bytes,
+            rss_file_bytes: self.rss_file_bytes + rhs.rss_file_bytes,
+            rss_shmem_bytes: self.rss_shmem_bytes + rhs.rss_shmem_bytes,
+            swap_bytes: self.swap_bytes + rhs.swap_bytes,
+            gpu_vram_bytes: self.gpu_vram_bytes + rhs.gpu_vram_bytes,
         }
     }
 }
@@ -323,85 +1990,197 @@ impl AddAssign for ProcStat {
         *self = *self + rhs;
     }
 }
-
-#[derive(Debug)]
-pub struct UserInfo {
-    pub name: String,
-    pub uid: u16,
-    pub gid: u16,
-    pub description: String,
-    pub kind: UserKind,
-}
-#[derive(Clone, Debug)]
-pub enum UserKind {
-    Root,
-    User,
-    Nologin,
-}
-impl UserInfo {
-    fn new(line: &str) -> Self {
-        let mut words = line.trim().split(":");
-        let name = words.next().unwrap().to_owned();
-        let _x = words.next().unwrap();
-        let uid = words.next().unwrap().parse().unwrap();
-        let gid = words.next().unwrap().parse().unwrap();
-        let description = words.next().unwrap().to_owned();
-        let _home = words.next().unwrap();
-        let nologin = words.next().unwrap().ends_with("nologin");
+impl Sub for ProcStat {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
         Self {
-            name,
-            uid,
-            gid,
-            description,
-            kind: if uid == 0 {
-                UserKind::Root
-            } else if nologin {
-                UserKind::Nologin
-            } else {
-                UserKind::User
-            },
+            guest_time_millis: self.guest_time_millis - rhs.guest_time_millis,
+            user_time_millis: self.user_time_millis - rhs.user_time_millis,
+            system_time_millis: self.system_time_millis - rhs.system_time_millis,
+            children_time_millis: self.children_time_millis - rhs.children_time_millis,
+            disk_read_bytes_per_second: self.disk_read_bytes_per_second
+                - rhs.disk_read_bytes_per_second,
+            disk_write_bytes_per_second: self.disk_write_bytes_per_second
+                - rhs.disk_write_bytes_per_second,
+            net_send_bytes_per_second: self.net_send_bytes_per_second
+                - rhs.net_send_bytes_per_second,
+            net_receive_bytes_per_second: self.net_receive_bytes_per_second
+                - rhs.net_receive_bytes_per_second,
+            minor_faults_per_second: self.minor_faults_per_second - rhs.minor_faults_per_second,
+            major_faults_per_second: self.major_faults_per_second - rhs.major_faults_per_second,
+            voluntary_switches_per_second: self.voluntary_switches_per_second
+                - rhs.voluntary_switches_per_second,
+            involuntary_switches_per_second: self.involuntary_switches_per_second
+                - rhs.involuntary_switches_per_second,
+            run_delay_millis: self.run_delay_millis - rhs.run_delay_millis,
+            gpu_time_millis: self.gpu_time_millis - rhs.gpu_time_millis,
+            mem_bytes: self.mem_bytes - rhs.mem_bytes,
+            pss_bytes: self.pss_bytes - rhs.pss_bytes,
+            uss_bytes: self.uss_bytes - rhs.uss_bytes,
+            rss_anon_bytes: self.rss_anon_bytes - rhs.rss_anon_bytes,
+            rss_file_bytes: self.rss_file_bytes - rhs.rss_file_bytes,
+            rss_shmem_bytes: self.rss_shmem_bytes - rhs.rss_shmem_bytes,
+            swap_bytes: self.swap_bytes - rhs.swap_bytes,
+            gpu_vram_bytes: self.gpu_vram_bytes - rhs.gpu_vram_bytes,
         }
     }
 }
-#[derive(Debug)]
-#[allow(unused)]
-pub struct GroupInfo {
-    pub name: String,
-    pub gid: u16,
-    pub users: Vec<String>,
-}
-impl GroupInfo {
-    fn new(line: &str) -> Self {
-        let mut words = line.trim().split(":");
-        let name = words.next().unwrap().to_owned();
-        let _x = words.next().unwrap();
-        let gid = words.next().unwrap().parse().unwrap();
-        let users = words
-            .next()
-            .unwrap()
-            .split(",")
-            .map(str::to_owned)
-            .collect();
-        Self { name, gid, users }
+impl SubAssign for ProcStat {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
     }
 }
 
 #[derive(Default, Debug)]
 pub struct StringArena {
     arena: Vec<String>,
+    by_string: HashMap<String, StringArenaHandle>,
 }
-#[derive(Debug, Clone, Copy)]
+/// Equal for equal strings of the same arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct StringArenaHandle {
     idx: usize,
 }
 impl StringArena {
-    fn push(&mut self, s: String) -> StringArenaHandle {
-        self.arena.push(s);
-        StringArenaHandle {
-            idx: self.arena.len() - 1,
+    fn intern(&mut self, s: &str) -> StringArenaHandle {
+        if let Some(&h) = self.by_string.get(s) {
+            return h;
+        }
+        let h = StringArenaHandle {
+            idx: self.arena.len(),
+        };
+        self.arena.push(s.to_owned());
+        self.by_string.insert(s.to_owned(), h);
+        h
+    }
+    fn len(&self) -> usize {
+        self.arena.len()
+    }
+    /// Keeps only the strings of `handles`, which are updated to point into the new arena.
+    fn compact<'a>(&mut self, handles: impl Iterator<Item = &'a mut StringArenaHandle>) {
+        let old = std::mem::take(self);
+        for h in handles {
+            *h = self.intern(old.get(*h));
         }
     }
     pub fn get(&self, h: StringArenaHandle) -> &str {
         &self.arena[h.idx]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Reverse;
+
+    /// Processes in fixed cgroups, using only `mem_bytes`.
+    const CGROUPS: [(&str, u64); 5] = [
+        ("/init.scope", 1),
+        ("/system.slice/sshd.service", 2),
+        ("/system.slice/cron.service", 4),
+        ("/user.slice/user-1000.slice/session-2.scope", 8),
+        (
+            "/user.slice/user-1000.slice/user@1000.service/app.slice/app-x.scope",
+            16,
+        ),
+    ];
+
+    fn units() -> Vec<UnitInfo> {
+        let mut tally = Tally::default();
+        let mut units = Vec::new();
+        for (cgroup, mem_bytes) in CGROUPS {
+            for path in cgroupfs::unit_paths(cgroup) {
+                let i = tally.count_in(&mut units, path, || UnitInfo::new(path));
+                units[i].stat.mem_bytes += mem_bytes;
+            }
+        }
+        units
+    }
+
+    fn paths(units: &[UnitInfo]) -> Vec<&str> {
+        units.iter().map(|u| u.path.as_str()).collect()
+    }
+
+    #[test]
+    fn units_sum_contained_cgroups() {
+        let mut units = units();
+        units.sort_by(|a, b| Ord::cmp(&a.path, &b.path));
+        let summary: Vec<(&str, &str, usize, u64)> = units
+            .iter()
+            .map(|u| (u.path.as_str(), u.name.as_str(), u.depth, u.stat.mem_bytes))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("/init.scope", "init.scope", 0, 1),
+                ("/system.slice", "system.slice", 0, 6),
+                ("/system.slice/cron.service", "cron.service", 1, 4),
+                ("/system.slice/sshd.service", "sshd.service", 1, 2),
+                ("/user.slice", "user.slice", 0, 24),
+                ("/user.slice/user-1000.slice", "user-1000.slice", 1, 24),
+                (
+                    "/user.slice/user-1000.slice/session-2.scope",
+                    "session-2.scope",
+                    2,
+                    8
+                ),
+                (
+                    "/user.slice/user-1000.slice/user@1000.service",
+                    "user@1000.service",
+                    2,
+                    16
+                ),
+                (
+                    "/user.slice/user-1000.slice/user@1000.service/app.slice",
+                    "app.slice",
+                    3,
+                    16
+                ),
+                (
+                    "/user.slice/user-1000.slice/user@1000.service/app.slice/app-x.scope",
+                    "app-x.scope",
+                    4,
+                    16
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn units_sorted_as_tree() {
+        let mut units = units();
+        sort_units_as_tree(&mut units, |_| ());
+        assert_eq!(
+            paths(&units),
+            [
+                "/init.scope",
+                "/system.slice",
+                "/system.slice/cron.service",
+                "/system.slice/sshd.service",
+                "/user.slice",
+                "/user.slice/user-1000.slice",
+                "/user.slice/user-1000.slice/session-2.scope",
+                "/user.slice/user-1000.slice/user@1000.service",
+                "/user.slice/user-1000.slice/user@1000.service/app.slice",
+                "/user.slice/user-1000.slice/user@1000.service/app.slice/app-x.scope",
+            ]
+        );
+        // Siblings are sorted by the key, while children stay below their parent
+        sort_units_as_tree(&mut units, |u| Reverse(u.stat.mem_bytes));
+        assert_eq!(
+            paths(&units),
+            [
+                "/user.slice",
+                "/user.slice/user-1000.slice",
+                "/user.slice/user-1000.slice/user@1000.service",
+                "/user.slice/user-1000.slice/user@1000.service/app.slice",
+                "/user.slice/user-1000.slice/user@1000.service/app.slice/app-x.scope",
+                "/user.slice/user-1000.slice/session-2.scope",
+                "/system.slice",
+                "/system.slice/cron.service",
+                "/system.slice/sshd.service",
+                "/init.scope",
+            ]
+        );
+    }
+}
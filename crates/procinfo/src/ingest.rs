@@ -1,20 +1,175 @@
-use crate::procfs;
+use crate::{
+    cgroupfs,
+    connector::{ProcConnector, ProcEvent},
+    container::{ContainerId, ContainerRuntime},
+    control, procfs,
+    sockdiag::{SockDiag, TcpBytes},
+};
 use either::Either;
-use std::{collections::BTreeMap, process::Command};
+use nix::sched::CpuSet;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    thread,
+};
 
 pub struct ProcIngest {
+    options: ProcIngestOptions,
     pub by_pid: BTreeMap<u32, ProcessIngest>,
+    pub by_login_session: BTreeMap<u32, LoginSessionIngest>,
+    /// `None` if cgroup v2 is not mounted.
+    cgroup_root: Option<PathBuf>,
+    /// Every cgroup containing a live process, and their ancestors.
+    pub by_cgroup: BTreeMap<String, CgroupIngest>,
+    /// `None` if `/proc/self/ns` is unreadable, in which case only containers recognized by
+    /// their cgroup are found.
+    own_namespaces: Option<procfs::Namespaces>,
+    /// Every container containing a live process.
+    pub by_container: BTreeMap<ContainerId, ContainerIngest>,
+    /// Processes that exited during the last update, including those whose pid was reused.
+    pub exited: Vec<(u32, ExitedProcessIngest)>,
+    /// Pids that were in `by_pid` before the last update, but no longer are.
+    pub removed_pids: Vec<u32>,
+    /// Tids that were in the `by_tid` of a process before the last update, but no longer are.
+    /// Includes those of removed processes, and those since reused by another process.
+    pub removed_tids: Vec<u32>,
+    /// Pids in `by_pid` that are new since before the last update, or that any value of changed
+    /// for, in order. Idle processes are left out, as only their totals grow.
+    pub changed_pids: Vec<u32>,
+    /// `None` if not enabled by `ProcIngestOptions::proc_events`, or if the proc connector is
+    /// unavailable.
+    proc_events: Option<Arc<Mutex<ProcEventsIngest>>>,
+    /// `None` if `NETLINK_SOCK_DIAG` is unavailable.
+    sock_diag: Option<SockDiag>,
+    tcp_bytes_by_inode: HashMap<u64, TcpBytes>,
+}
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcIngestOptions {
+    /// Read PSS and USS from `/proc/{pid}/smaps_rollup`.
+    pub smaps_rollup: bool,
+    /// Also sample processes as they fork and exec, as reported by the proc connector, so that
+    /// those exiting between updates appear in `ProcIngest::exited`. Requires `CAP_NET_ADMIN`.
+    pub proc_events: bool,
+}
+/// Processes not yet seen by any update, sampled by a thread receiving proc connector events.
+#[derive(Default)]
+struct ProcEventsIngest {
+    /// At most `MAX_LIVE_SAMPLES`.
+    live: BTreeMap<u32, ProcessSample>,
+    exited: Vec<(u32, ExitedProcessIngest)>,
+}
+/// Bounds the files held open by `ProcEventsIngest`, two per sample, during fork storms.
+const MAX_LIVE_SAMPLES: usize = 1024;
+/// Only what is needed to list a process once it exits, read as it forks or execs.
+struct ProcessSample {
+    process: ExitedProcessIngest,
+    stat: procfs::TidStat,
+    io: Option<procfs::TidIo>,
+}
+pub struct LoginSessionIngest {
+    /// The login uid of some member process, used if the session file is missing.
+    pub uid: Option<u32>,
+    pub systemd: Option<procfs::SystemdSession>,
+}
+pub struct ContainerIngest {
+    /// `None` if in our uts or mount namespace, or if its root filesystem is not ours to inspect.
+    pub hostname: Option<String>,
+    /// Of the lowest pid in the container when first seen.
+    pub namespaces: Option<procfs::Namespaces>,
+}
+pub struct CgroupIngest {
+    pub files: Option<cgroupfs::CgroupFiles>,
+    cumulative: cgroupfs::CgroupCounters,
+    pub user_time_us: u64,
+    pub system_time_us: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub memory_bytes: u64,
+    pub pids: u64,
 }
 pub struct ProcessIngest {
     pub kernel: bool,
     pub name: String,
     pub cmdline: Option<String>,
+    /// Audit session id, as assigned by `pam_loginuid`.
+    pub login_session: Option<u32>,
+    pub login_uid: Option<u32>,
+    pub cgroup_file: procfs::PidCgroup,
+    /// The cgroup v2 path, such as `/user.slice/user-1000.slice/session-2.scope`.
+    pub cgroup: Option<String>,
+    /// Sometimes requires `PTRACE_MODE_READ_FSCREDS`. `None` for kernel threads.
+    pub namespaces: Option<procfs::Namespaces>,
+    /// The pid as seen from within its innermost pid namespace, if that is not ours.
+    pub ns_pid: Option<u32>,
+    /// Set by `ProcIngest::update`.
+    pub container: Option<ContainerId>,
     pub by_tid: BTreeMap<u32, ThreadIngest>,
+    /// Since boot. Along with the pid, this identifies the process.
+    pub start_time_ms: u64,
+
+    /// Unlike summing over `by_tid`, includes the time of threads that exited since last update.
+    pub stat: procfs::TidStat,
+    pub cumulative_user_time_ms: u64,
+    pub cumulative_system_time_ms: u64,
+    cumulative_guest_time_ms: u64,
+    cumulative_children_time_ms: u64,
+    pub user_time_ms: u32,
+    pub system_time_ms: u32,
+    pub guest_time_ms: u32,
+    /// User and system time of children reaped since last update.
+    pub children_time_ms: u32,
+
+    /// Sometimes requires `PTRACE_MODE_READ_FSCREDS`. Includes threads that have exited.
+    pub io: Option<procfs::TidIo>,
+    pub cumulative_read_bytes: u64,
+    pub cumulative_write_bytes: u64,
 
     pub status: procfs::PidStatus,
-    pub uid: u16,
-    pub gid: u16,
-    pub vm_rss_kb: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub mem_kb: procfs::StatusMemKb,
+
+    /// Only present if enabled by `ProcIngestOptions::smaps_rollup`. Sometimes requires
+    /// `PTRACE_MODE_READ_FSCREDS`.
+    pub smaps_rollup: Option<procfs::PidSmapsRollup>,
+    pub pss_kb: u64,
+    pub uss_kb: u64,
+
+    /// Sometimes requires `PTRACE_MODE_READ_FSCREDS`. Includes clients shared with other
+    /// processes.
+    pub drm_clients: Vec<procfs::DrmClient>,
+    /// Engine time of each of `drm_clients` since last update.
+    drm_engine_ns: Vec<u64>,
+    /// Summed over `drm_clients` not shared with a lower pid, so that each client counts once.
+    pub gpu_time_ns: u64,
+    pub gpu_vram_bytes: u64,
+
+    /// Sometimes requires `PTRACE_MODE_READ_FSCREDS`. Includes sockets shared with other
+    /// processes.
+    pub socket_inodes: Vec<u64>,
+    /// Summed over TCP sockets in our network namespace not shared with a lower pid, as for
+    /// `gpu_time_ns`. Payload bytes since last update, missing those of sockets closed since.
+    pub net_send_bytes: u64,
+    pub net_receive_bytes: u64,
+
+    /// Whether the process is new, or any of the values above changed since last update.
+    changed: bool,
+}
+/// What is kept of a process that exited, without any open files.
+pub struct ExitedProcessIngest {
+    pub name: String,
+    pub cmdline: Option<String>,
+    pub uid: u32,
+    /// Since boot.
+    pub start_time_ms: u64,
+    pub cumulative_user_time_ms: u64,
+    pub cumulative_system_time_ms: u64,
+    pub peak_mem_kb: u64,
+    pub cumulative_read_bytes: u64,
+    pub cumulative_write_bytes: u64,
 }
 pub struct ThreadIngest {
     /// Sometimes requires `PTRACE_MODE_READ_FSCREDS`.
@@ -24,17 +179,47 @@ pub struct ThreadIngest {
     pub read_bytes: u64,
     pub write_bytes: u64,
 
+    pub status: Option<procfs::TidStatus>,
+    cumulative_voluntary_switches: u64,
+    cumulative_involuntary_switches: u64,
+    pub voluntary_switches: u64,
+    pub involuntary_switches: u64,
+
+    pub schedstat: Option<procfs::TidSchedstat>,
+    cumulative_run_delay_ns: u64,
+    pub run_delay_ns: u64,
+
     pub stat: procfs::TidStat,
+    pub name: String,
+    pub state: char,
+    pub ppid: u32,
     pub sid: u32,
+    pub tty_nr: u32,
+    pub num_threads: u32,
+    pub start_time_ms: u64,
     cumulative_user_time_ms: u64,
     cumulative_system_time_ms: u64,
     cumulative_guest_time_ms: u64,
+    cumulative_minor_faults: u64,
+    cumulative_major_faults: u64,
     pub user_time_ms: u32,
     pub system_time_ms: u32,
     pub guest_time_ms: u32,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    pub nice: i8,
+    pub rt_priority: u8,
+    pub policy: u32,
+
+    /// `None` if the thread exited while reading it.
+    pub io_priority: Option<control::IoPriority>,
+    pub affinity: Option<CpuSet>,
+
+    /// Whether the thread is new, or any of the values above changed since last update.
+    changed: bool,
 }
 impl ProcIngest {
-    pub fn new() -> Self {
+    pub fn new(options: ProcIngestOptions) -> Self {
         let user_hz: u32 = {
             let output = Command::new("getconf").arg("CLK_TCK").output().unwrap();
             assert!(output.status.success());
@@ -45,7 +230,10 @@ impl ProcIngest {
                 .unwrap()
         };
         assert_eq!(user_hz, 100);
-        let fd_limit = 8196;
+        // Every thread holds several files open
+        let (_, hard_fd_limit) =
+            nix::sys::resource::getrlimit(nix::sys::resource::Resource::RLIMIT_NOFILE).unwrap();
+        let fd_limit = hard_fd_limit.max(8196);
         nix::sys::resource::setrlimit(
             nix::sys::resource::Resource::RLIMIT_NOFILE,
             fd_limit,
@@ -54,51 +242,630 @@ impl ProcIngest {
         .unwrap();
 
         let mut ret = Self {
+            options,
             by_pid: BTreeMap::new(),
+            by_login_session: BTreeMap::new(),
+            cgroup_root: cgroupfs::find_cgroup2_root(),
+            by_cgroup: BTreeMap::new(),
+            own_namespaces: procfs::get_own_namespaces(),
+            by_container: BTreeMap::new(),
+            exited: Vec::new(),
+            removed_pids: Vec::new(),
+            removed_tids: Vec::new(),
+            changed_pids: Vec::new(),
+            proc_events: match options.proc_events {
+                true => ProcEventsIngest::start(),
+                false => None,
+            },
+            sock_diag: SockDiag::open()
+                .map_err(|err| tracing::warn!("no per-process network usage: {err}"))
+                .ok(),
+            tcp_bytes_by_inode: HashMap::new(),
         };
         ret.update();
         ret
     }
     pub fn update(&mut self) {
+        let mut old_by_pid = std::mem::take(&mut self.by_pid);
+        let old_pids: Vec<u32> = old_by_pid.keys().copied().collect();
+        let old_tids: Vec<(u32, u32)> = old_by_pid
+            .iter()
+            .flat_map(|(&pid, process)| process.by_tid.keys().map(move |&tid| (pid, tid)))
+            .collect();
+        self.exited = Vec::new();
         self.by_pid = procfs::get_live_pids()
             .filter_map(|pid| {
+                let old = old_by_pid.remove(&pid);
                 Some((
                     pid,
-                    ProcessIngest::new_from_old(pid, self.by_pid.remove(&pid))?,
+                    ProcessIngest::new_from_old(pid, old, self.options, &mut self.exited)?,
                 ))
             })
             .collect();
+        self.exited
+            .extend(old_by_pid.into_iter().map(|(pid, old)| (pid, old.into())));
+        self.removed_pids = old_pids
+            .into_iter()
+            .filter(|pid| !self.by_pid.contains_key(pid))
+            .collect();
+        self.removed_tids = old_tids
+            .into_iter()
+            .filter(|(pid, tid)| {
+                !self
+                    .by_pid
+                    .get(pid)
+                    .is_some_and(|process| process.by_tid.contains_key(tid))
+            })
+            .map(|(_, tid)| tid)
+            .collect();
+        // Clients and sockets are shared after `fork`, or when passing file descriptors
+        let tcp_bytes_by_inode = self
+            .sock_diag
+            .as_mut()
+            .map_or_else(HashMap::new, |sock_diag| sock_diag.get_tcp_bytes_by_inode());
+        let mut seen_drm_clients = HashSet::new();
+        let mut seen_sockets = HashSet::new();
+        for process in self.by_pid.values_mut() {
+            let gpu_vram_bytes = process.gpu_vram_bytes;
+            process.gpu_time_ns = 0;
+            process.gpu_vram_bytes = 0;
+            for (client, engine_ns) in process.drm_clients.iter().zip(&process.drm_engine_ns) {
+                if seen_drm_clients.insert((client.pdev.clone(), client.client_id)) {
+                    process.gpu_time_ns += engine_ns;
+                    process.gpu_vram_bytes += client.vram_bytes;
+                }
+            }
+            process.net_send_bytes = 0;
+            process.net_receive_bytes = 0;
+            for inode in &process.socket_inodes {
+                let Some(new) = tcp_bytes_by_inode.get(inode) else {
+                    continue;
+                };
+                if seen_sockets.insert(*inode) {
+                    let old = self
+                        .tcp_bytes_by_inode
+                        .get(inode)
+                        .copied()
+                        .unwrap_or_default();
+                    process.net_send_bytes += new.sent.saturating_sub(old.sent);
+                    process.net_receive_bytes += new.received.saturating_sub(old.received);
+                }
+            }
+            process.changed |= process.gpu_time_ns != 0
+                || process.gpu_vram_bytes != gpu_vram_bytes
+                || process.net_send_bytes != 0
+                || process.net_receive_bytes != 0;
+        }
+        self.tcp_bytes_by_inode = tcp_bytes_by_inode;
+        if let Some(proc_events) = &self.proc_events {
+            proc_events
+                .lock()
+                .unwrap()
+                .drain_into(&self.by_pid, &mut self.exited);
+        }
+
+        let mut old_by_login_session = std::mem::take(&mut self.by_login_session);
+        for process in self.by_pid.values() {
+            let Some(id) = process.login_session else {
+                continue;
+            };
+            self.by_login_session.entry(id).or_insert_with(|| {
+                old_by_login_session
+                    .remove(&id)
+                    .unwrap_or_else(|| LoginSessionIngest {
+                        uid: process.login_uid,
+                        systemd: procfs::SystemdSession::new(id),
+                    })
+            });
+        }
+
+        let mut old_by_cgroup = std::mem::take(&mut self.by_cgroup);
+        for process in self.by_pid.values() {
+            let mut path = process.cgroup.as_deref();
+            while let Some(p) = path {
+                if self.by_cgroup.contains_key(p) {
+                    // Ancestors were added along with it
+                    break;
+                }
+                let cgroup = old_by_cgroup
+                    .remove(p)
+                    .unwrap_or_else(|| CgroupIngest::new(self.cgroup_root.as_deref(), p));
+                self.by_cgroup.insert(p.to_owned(), cgroup);
+                path = cgroupfs::parent_path(p);
+            }
+        }
+        for cgroup in self.by_cgroup.values_mut() {
+            cgroup.update();
+        }
+
+        let mut old_by_container = std::mem::take(&mut self.by_container);
+        for (&pid, process) in &mut self.by_pid {
+            let container = process
+                .cgroup
+                .as_deref()
+                .and_then(ContainerId::from_cgroup)
+                .or_else(|| {
+                    let pid_ns = process.namespaces?.pid;
+                    (pid_ns != self.own_namespaces?.pid).then(|| ContainerId {
+                        runtime: ContainerRuntime::PidNamespace,
+                        id: pid_ns.to_string(),
+                    })
+                });
+            process.changed |= container != process.container;
+            process.container = container;
+            let Some(id) = &process.container else {
+                continue;
+            };
+            if !self.by_container.contains_key(id) {
+                let container = old_by_container.remove(id).unwrap_or_else(|| {
+                    ContainerIngest::new(pid, process.namespaces, self.own_namespaces)
+                });
+                self.by_container.insert(id.clone(), container);
+            }
+        }
+        self.changed_pids = self
+            .by_pid
+            .iter()
+            .filter(|(_, process)| process.changed)
+            .map(|(&pid, _)| pid)
+            .collect();
+    }
+}
+impl ProcEventsIngest {
+    fn start() -> Option<Arc<Mutex<Self>>> {
+        let mut connector = match ProcConnector::open() {
+            Ok(connector) => connector,
+            Err(err) => {
+                tracing::warn!("proc connector unavailable, only scanning /proc: {err}");
+                return None;
+            }
+        };
+        let ret = Arc::new(Mutex::new(Self::default()));
+        let weak = Arc::downgrade(&ret);
+        thread::Builder::new()
+            .name("proc-events".to_owned())
+            .spawn(move || loop {
+                let events = connector.recv();
+                let Some(shared) = weak.upgrade() else {
+                    return;
+                };
+                for event in events {
+                    match event {
+                        // After exec, the name and cmdline of the earlier sample are outdated. Both
+                        // often fail for processes living for only a millisecond or so.
+                        ProcEvent::Fork { pid } | ProcEvent::Exec { pid } => {
+                            let full = {
+                                let shared = shared.lock().unwrap();
+                                shared.live.len() >= MAX_LIVE_SAMPLES
+                                    && !shared.live.contains_key(&pid)
+                            };
+                            if full {
+                                continue;
+                            }
+                            if let Some(new) = ProcessSample::new(pid) {
+                                shared.lock().unwrap().live.insert(pid, new);
+                            }
+                        }
+                        ProcEvent::Exit { pid } => {
+                            let mut shared = shared.lock().unwrap();
+                            if let Some(sample) = shared.live.remove(&pid) {
+                                shared.exited.push((pid, sample.into_exited()));
+                            }
+                        }
+                    }
+                }
+            })
+            .unwrap();
+        Some(ret)
+    }
+    /// Moves processes that exited without being reported by the scan that produced `by_pid`
+    /// to `exited`, and stops tracking those the scan saw.
+    fn drain_into(
+        &mut self,
+        by_pid: &BTreeMap<u32, ProcessIngest>,
+        exited: &mut Vec<(u32, ExitedProcessIngest)>,
+    ) {
+        let scanned = |pid: u32, start_time_ms: u64, other: Option<&ExitedProcessIngest>| {
+            other.is_some_and(|other| other.start_time_ms == start_time_ms)
+                || by_pid
+                    .get(&pid)
+                    .is_some_and(|p| p.start_time_ms == start_time_ms)
+        };
+        for (pid, process) in std::mem::take(&mut self.exited) {
+            let scanned_exit = exited.iter().find(|(p, _)| *p == pid).map(|(_, p)| p);
+            if !scanned(pid, process.start_time_ms, scanned_exit) {
+                exited.push((pid, process));
+            }
+        }
+        for (pid, mut sample) in std::mem::take(&mut self.live) {
+            let start_time_ms = sample.process.start_time_ms;
+            if scanned(pid, start_time_ms, None) {
+                continue;
+            }
+            match sample.stat.get_fields() {
+                Some(stat) if stat.start_time_ms == start_time_ms => {
+                    self.live.insert(pid, sample);
+                }
+                // Its exit event was dropped
+                _ => exited.push((pid, sample.into_exited())),
+            }
+        }
+    }
+}
+impl ContainerIngest {
+    fn new(
+        pid: u32,
+        namespaces: Option<procfs::Namespaces>,
+        own_namespaces: Option<procfs::Namespaces>,
+    ) -> Self {
+        // Without its own uts namespace it has our hostname, and without its own mount
+        // namespace we would read our own `/etc/hostname`
+        let ours = matches!(
+            (namespaces, own_namespaces),
+            (Some(ns), Some(own)) if ns.uts == own.uts || ns.mnt == own.mnt
+        );
+        Self {
+            hostname: match ours {
+                true => None,
+                false => procfs::get_hostname(pid),
+            },
+            namespaces,
+        }
+    }
+}
+impl CgroupIngest {
+    fn new(root: Option<&Path>, path: &str) -> Self {
+        let mut files = root.and_then(|root| cgroupfs::CgroupFiles::new(root, path));
+        Self {
+            cumulative: files
+                .as_mut()
+                .map_or_else(Default::default, |files| files.get_counters()),
+            files,
+            user_time_us: 0,
+            system_time_us: 0,
+            read_bytes: 0,
+            write_bytes: 0,
+            memory_bytes: 0,
+            pids: 0,
+        }
+    }
+    fn update(&mut self) {
+        let Some(files) = self.files.as_mut() else {
+            return;
+        };
+        let new = files.get_counters();
+        let old = self.cumulative;
+        self.user_time_us = new
+            .cumulative_user_usec
+            .saturating_sub(old.cumulative_user_usec);
+        self.system_time_us = new
+            .cumulative_system_usec
+            .saturating_sub(old.cumulative_system_usec);
+        self.read_bytes = new
+            .cumulative_read_bytes
+            .saturating_sub(old.cumulative_read_bytes);
+        self.write_bytes = new
+            .cumulative_write_bytes
+            .saturating_sub(old.cumulative_write_bytes);
+        self.memory_bytes = new.memory_bytes;
+        self.pids = new.pids;
+        self.cumulative = new;
     }
 }
 impl ProcessIngest {
-    fn new_from_old(pid: u32, old: Option<Self>) -> Option<Self> {
-        let mut old = old.or_else(|| {
-            let (kernel, name, cmdline) = procfs::get_is_kernel_name_cmdline(pid)?;
-            Some(Self {
-                kernel,
-                name,
-                cmdline,
-                by_tid: BTreeMap::new(),
-                status: procfs::PidStatus::new(pid, kernel)?,
-                uid: 0,
-                gid: 0,
-                vm_rss_kb: 0,
+    fn new(pid: u32, options: ProcIngestOptions) -> Option<Self> {
+        let (kernel, name, cmdline) = procfs::get_is_kernel_name_cmdline(pid)?;
+        let (login_session, login_uid) = procfs::get_sessionid_loginuid(pid);
+        Some(Self {
+            kernel,
+            name,
+            cmdline,
+            login_session,
+            login_uid,
+            cgroup_file: procfs::PidCgroup::new(pid)?,
+            cgroup: None,
+            namespaces: None,
+            ns_pid: None,
+            container: None,
+            by_tid: BTreeMap::new(),
+            start_time_ms: 0,
+            stat: procfs::TidStat::new_process(pid)?,
+            cumulative_user_time_ms: 0,
+            cumulative_system_time_ms: 0,
+            cumulative_guest_time_ms: 0,
+            cumulative_children_time_ms: 0,
+            user_time_ms: 0,
+            system_time_ms: 0,
+            guest_time_ms: 0,
+            children_time_ms: 0,
+            io: procfs::TidIo::new_process(pid),
+            cumulative_read_bytes: 0,
+            cumulative_write_bytes: 0,
+            status: procfs::PidStatus::new(pid, kernel)?,
+            uid: 0,
+            gid: 0,
+            mem_kb: procfs::StatusMemKb::default(),
+            smaps_rollup: match options.smaps_rollup && !kernel {
+                true => procfs::PidSmapsRollup::new(pid),
+                false => None,
+            },
+            pss_kb: 0,
+            uss_kb: 0,
+            drm_clients: Vec::new(),
+            drm_engine_ns: Vec::new(),
+            gpu_time_ns: 0,
+            gpu_vram_bytes: 0,
+            socket_inodes: Vec::new(),
+            net_send_bytes: 0,
+            net_receive_bytes: 0,
+            changed: true,
+        })
+    }
+    /// Returns `None` if the process has exited. Moves `old` to `exited` if it no longer is the
+    /// process with this pid, or if it exited while being read.
+    fn new_from_old(
+        pid: u32,
+        old: Option<Self>,
+        options: ProcIngestOptions,
+        exited: &mut Vec<(u32, ExitedProcessIngest)>,
+    ) -> Option<Self> {
+        let old_and_stat = old.and_then(|mut old| match old.stat.get_fields() {
+            Some(stat) if stat.start_time_ms == old.start_time_ms => Some((old, stat)),
+            // The process exited, and the pid was possibly reused by a new process. Either way,
+            // the cached files refer to the exited process.
+            _ => {
+                exited.push((pid, old.into()));
+                None
+            }
+        });
+        let (mut old, stat, seen) = match old_and_stat {
+            Some((old, stat)) => (old, stat, true),
+            None => {
+                let mut new = Self::new(pid, options)?;
+                let stat = new.stat.get_fields()?;
+                (new, stat, false)
+            }
+        };
+        let by_tid = mem::take(&mut old.by_tid);
+        let num_old_threads = by_tid.len();
+        let reads = (|| {
+            let status = old.status.get_uid_gid_nspid_mem_threads()?;
+            let read_write_bytes = match old.io.as_mut() {
+                Some(io) => io.get_cumulative_read_write_bytes()?,
+                None => (0, 0),
+            };
+            let cgroup = old.cgroup_file.get_path()?;
+            let by_tid = ThreadIngest::new_by_tid(pid, by_tid, status.4 == 1)?;
+            Some((status, read_write_bytes, cgroup, by_tid))
+        })();
+        let Some((
+            (uid, gid, ns_pid, mem_kb, _),
+            (cumulative_read_bytes, cumulative_write_bytes),
+            cgroup,
+            by_tid,
+        )) = reads
+        else {
+            // The process exited while being read. Without totals, one never seen before is
+            // not worth listing.
+            if seen {
+                old.cumulative_user_time_ms = stat.cumulative_user_time_ms;
+                old.cumulative_system_time_ms = stat.cumulative_system_time_ms;
+                exited.push((pid, old.into()));
+            }
+            return None;
+        };
+        // Re-read every update, as container runtimes enter namespaces after forking
+        let namespaces = match old.kernel {
+            true => None,
+            false => procfs::get_namespaces(pid),
+        };
+        let cumulative_children_time_ms =
+            stat.cumulative_children_user_time_ms + stat.cumulative_children_system_time_ms;
+        // Unreadable after a setuid exec, for example, which doesn't mean the process exited
+        let (pss_kb, uss_kb) = old
+            .smaps_rollup
+            .as_mut()
+            .and_then(|smaps_rollup| smaps_rollup.get_pss_uss_kb())
+            .unwrap_or_default();
+        let fd_usage = match old.kernel {
+            true => procfs::FdUsage::default(),
+            false => procfs::get_fd_usage(pid).unwrap_or_default(),
+        };
+        let drm_clients = fd_usage.drm_clients;
+        let drm_engine_ns = drm_clients
+            .iter()
+            .map(|client| {
+                client.engine_ns_since(
+                    old.drm_clients
+                        .iter()
+                        .find(|c| c.client_id == client.client_id && c.pdev == client.pdev),
+                )
             })
-        })?;
-        let (uid, gid, vm_rss_kb, threads) = old.status.get_uid_gid_vm_rss_kb_threads()?;
-        Some(ProcessIngest {
+            .collect();
+        // Rates are compared by whether they were or are nonzero, as they are once active
+        let changed = !seen
+            || old.is_active()
+            || by_tid.len() != num_old_threads
+            || by_tid.values().any(|thread| thread.changed)
+            || (uid, gid, ns_pid, mem_kb, pss_kb, uss_kb)
+                != (
+                    old.uid, old.gid, old.ns_pid, old.mem_kb, old.pss_kb, old.uss_kb,
+                )
+            || cgroup != old.cgroup;
+        let mut new = ProcessIngest {
             kernel: old.kernel,
             name: old.name,
             cmdline: old.cmdline,
-            by_tid: ThreadIngest::new_by_tid(pid, old.by_tid, threads == 1)?,
+            login_session: old.login_session,
+            login_uid: old.login_uid,
+            cgroup_file: old.cgroup_file,
+            cgroup,
+            namespaces,
+            ns_pid,
+            container: old.container,
+            by_tid,
+            start_time_ms: stat.start_time_ms,
+            stat: old.stat,
+            cumulative_user_time_ms: stat.cumulative_user_time_ms,
+            cumulative_system_time_ms: stat.cumulative_system_time_ms,
+            cumulative_guest_time_ms: stat.cumulative_guest_time_ms,
+            cumulative_children_time_ms,
+            user_time_ms: stat
+                .cumulative_user_time_ms
+                .saturating_sub(old.cumulative_user_time_ms) as u32,
+            system_time_ms: stat
+                .cumulative_system_time_ms
+                .saturating_sub(old.cumulative_system_time_ms) as u32,
+            guest_time_ms: stat
+                .cumulative_guest_time_ms
+                .saturating_sub(old.cumulative_guest_time_ms) as u32,
+            children_time_ms: cumulative_children_time_ms
+                .saturating_sub(old.cumulative_children_time_ms)
+                as u32,
+            io: old.io,
+            cumulative_read_bytes,
+            cumulative_write_bytes,
             status: old.status,
-            uid: uid as u16,
-            gid: gid as u16,
-            vm_rss_kb,
+            uid,
+            gid,
+            mem_kb,
+            smaps_rollup: old.smaps_rollup,
+            pss_kb,
+            uss_kb,
+            drm_clients,
+            drm_engine_ns,
+            gpu_time_ns: 0,
+            // Compared against once summed again by `ProcIngest::update`
+            gpu_vram_bytes: old.gpu_vram_bytes,
+            socket_inodes: fd_usage.socket_inodes,
+            net_send_bytes: 0,
+            net_receive_bytes: 0,
+            changed,
+        };
+        new.changed |= new.is_active();
+        Some(new)
+    }
+    /// Whether any rate since last update is nonzero.
+    fn is_active(&self) -> bool {
+        self.user_time_ms != 0
+            || self.system_time_ms != 0
+            || self.guest_time_ms != 0
+            || self.children_time_ms != 0
+            || self.gpu_time_ns != 0
+            || self.net_send_bytes != 0
+            || self.net_receive_bytes != 0
+    }
+}
+impl From<ProcessIngest> for ExitedProcessIngest {
+    fn from(process: ProcessIngest) -> Self {
+        Self {
+            name: process.name,
+            cmdline: process.cmdline,
+            uid: process.uid,
+            start_time_ms: process.start_time_ms,
+            cumulative_user_time_ms: process.cumulative_user_time_ms,
+            cumulative_system_time_ms: process.cumulative_system_time_ms,
+            peak_mem_kb: process.mem_kb.vm_hwm,
+            cumulative_read_bytes: process.cumulative_read_bytes,
+            cumulative_write_bytes: process.cumulative_write_bytes,
+        }
+    }
+}
+impl ProcessSample {
+    /// Unlike `ProcessIngest::new`, keeps only `stat` and `io` open and skips the threads and
+    /// file descriptors. Returns `None` for kernel threads.
+    fn new(pid: u32) -> Option<Self> {
+        let (kernel, name, cmdline) = procfs::get_is_kernel_name_cmdline(pid)?;
+        // Zombies lack a cmdline, so would pass for kernel threads. Those rarely exit anyway.
+        if kernel {
+            return None;
+        }
+        let mut stat = procfs::TidStat::new_process(pid)?;
+        let fields = stat.get_fields()?;
+        let (uid, _, _, mem_kb, _) =
+            procfs::PidStatus::new(pid, kernel)?.get_uid_gid_nspid_mem_threads()?;
+        let mut io = procfs::TidIo::new_process(pid);
+        let (cumulative_read_bytes, cumulative_write_bytes) = io
+            .as_mut()
+            .and_then(|io| io.get_cumulative_read_write_bytes())
+            .unwrap_or_default();
+        Some(Self {
+            process: ExitedProcessIngest {
+                name,
+                cmdline,
+                uid,
+                start_time_ms: fields.start_time_ms,
+                cumulative_user_time_ms: fields.cumulative_user_time_ms,
+                cumulative_system_time_ms: fields.cumulative_system_time_ms,
+                peak_mem_kb: mem_kb.vm_hwm,
+                cumulative_read_bytes,
+                cumulative_write_bytes,
+            },
+            stat,
+            io,
         })
     }
+    /// Updates the totals of an exiting process, if it is still readable.
+    fn into_exited(mut self) -> ExitedProcessIngest {
+        if let Some(stat) = self.stat.get_fields() {
+            if stat.start_time_ms == self.process.start_time_ms {
+                self.process.cumulative_user_time_ms = stat.cumulative_user_time_ms;
+                self.process.cumulative_system_time_ms = stat.cumulative_system_time_ms;
+            }
+        }
+        if let Some((read, write)) = self
+            .io
+            .as_mut()
+            .and_then(|io| io.get_cumulative_read_write_bytes())
+        {
+            self.process.cumulative_read_bytes = read;
+            self.process.cumulative_write_bytes = write;
+        }
+        self.process
+    }
 }
 impl ThreadIngest {
+    fn new(pid: u32, tid: u32) -> Option<Self> {
+        Some(Self {
+            io: procfs::TidIo::new(pid, tid),
+            cumulative_read_bytes: 0,
+            cumulative_write_bytes: 0,
+            read_bytes: 0,
+            write_bytes: 0,
+            status: procfs::TidStatus::new(pid, tid),
+            cumulative_voluntary_switches: 0,
+            cumulative_involuntary_switches: 0,
+            voluntary_switches: 0,
+            involuntary_switches: 0,
+            schedstat: procfs::TidSchedstat::new(pid, tid),
+            cumulative_run_delay_ns: 0,
+            run_delay_ns: 0,
+            stat: procfs::TidStat::new(pid, tid)?,
+            name: String::new(),
+            state: '?',
+            ppid: 0,
+            sid: 0,
+            tty_nr: 0,
+            num_threads: 0,
+            start_time_ms: 0,
+            cumulative_user_time_ms: 0,
+            cumulative_system_time_ms: 0,
+            cumulative_guest_time_ms: 0,
+            cumulative_minor_faults: 0,
+            cumulative_major_faults: 0,
+            user_time_ms: 0,
+            system_time_ms: 0,
+            guest_time_ms: 0,
+            minor_faults: 0,
+            major_faults: 0,
+            nice: 0,
+            rt_priority: 0,
+            policy: 0,
+            io_priority: None,
+            affinity: None,
+            changed: true,
+        })
+    }
     fn new_by_tid(
         pid: u32,
         mut old: BTreeMap<u32, ThreadIngest>,
@@ -109,55 +876,132 @@ impl ThreadIngest {
             true => Either::Left([pid].into_iter()),
             false => Either::Right(procfs::get_live_tids(pid)),
         } {
-            let mut old = old.remove(&tid).or_else(|| {
-                Some(ThreadIngest {
-                    io: procfs::TidIo::new(pid, tid),
-                    cumulative_read_bytes: 0,
-                    cumulative_write_bytes: 0,
-                    read_bytes: 0,
-                    write_bytes: 0,
-                    stat: procfs::TidStat::new(pid, tid)?,
-                    sid: 0,
-                    cumulative_user_time_ms: 0,
-                    cumulative_system_time_ms: 0,
-                    cumulative_guest_time_ms: 0,
-                    user_time_ms: 0,
-                    system_time_ms: 0,
-                    guest_time_ms: 0,
-                })
-            })?;
+            // A reused tid is a new thread, as for pids
+            let old_and_stat = old.remove(&tid).and_then(|mut old| {
+                let stat = old.stat.get_fields()?;
+                (stat.start_time_ms == old.start_time_ms).then_some((old, stat))
+            });
+            let (mut old, stat, seen) = match old_and_stat {
+                Some((old, stat)) => (old, stat, true),
+                None => {
+                    let mut new = ThreadIngest::new(pid, tid)?;
+                    let stat = new.stat.get_fields()?;
+                    (new, stat, false)
+                }
+            };
             let (cumulative_read_bytes, cumulative_write_bytes) = match old.io.as_mut() {
                 Some(io) => io.get_cumulative_read_write_bytes()?,
                 None => (0, 0),
             };
-
-            let (sid, cumulative_user_time_ms, cumulative_system_time_ms, cumulative_guest_time_ms) =
-                old.stat.get_sid_cumulative_user_system_guest_time()?;
-            ret.insert(
-                tid,
-                ThreadIngest {
-                    io: old.io,
-                    cumulative_read_bytes,
-                    cumulative_write_bytes,
-                    read_bytes: cumulative_read_bytes.saturating_sub(old.cumulative_read_bytes),
-                    write_bytes: cumulative_write_bytes.saturating_sub(old.cumulative_write_bytes),
-                    stat: old.stat,
-                    sid,
-                    cumulative_user_time_ms,
-                    cumulative_system_time_ms,
-                    cumulative_guest_time_ms,
-                    user_time_ms: cumulative_user_time_ms
-                        .saturating_sub(old.cumulative_user_time_ms)
-                        as u32,
-                    system_time_ms: cumulative_system_time_ms
-                        .saturating_sub(old.cumulative_system_time_ms)
-                        as u32,
-                    guest_time_ms: cumulative_guest_time_ms
-                        .saturating_sub(old.cumulative_guest_time_ms)
-                        as u32,
-                },
-            );
+            let (cumulative_voluntary_switches, cumulative_involuntary_switches) =
+                match old.status.as_mut() {
+                    Some(status) => status.get_cumulative_context_switches()?,
+                    None => (0, 0),
+                };
+            let cumulative_run_delay_ns = match old.schedstat.as_mut() {
+                Some(schedstat) => schedstat.get_cumulative_run_delay_ns()?,
+                None => 0,
+            };
+            let io_priority = control::get_io_priority(tid);
+            let affinity = control::get_affinity(tid);
+            // Rates are compared by whether they were or are nonzero, as for processes
+            let changed = !seen
+                || old.is_active()
+                || (
+                    stat.name.as_str(),
+                    stat.state,
+                    stat.ppid,
+                    stat.sid,
+                    stat.tty_nr,
+                    stat.num_threads,
+                    stat.nice,
+                    stat.rt_priority,
+                    stat.policy,
+                    io_priority,
+                    affinity,
+                ) != (
+                    old.name.as_str(),
+                    old.state,
+                    old.ppid,
+                    old.sid,
+                    old.tty_nr,
+                    old.num_threads,
+                    old.nice,
+                    old.rt_priority,
+                    old.policy,
+                    old.io_priority,
+                    old.affinity,
+                );
+            let mut new = ThreadIngest {
+                io: old.io,
+                cumulative_read_bytes,
+                cumulative_write_bytes,
+                read_bytes: cumulative_read_bytes.saturating_sub(old.cumulative_read_bytes),
+                write_bytes: cumulative_write_bytes.saturating_sub(old.cumulative_write_bytes),
+                status: old.status,
+                cumulative_voluntary_switches,
+                cumulative_involuntary_switches,
+                voluntary_switches: cumulative_voluntary_switches
+                    .saturating_sub(old.cumulative_voluntary_switches),
+                involuntary_switches: cumulative_involuntary_switches
+                    .saturating_sub(old.cumulative_involuntary_switches),
+                schedstat: old.schedstat,
+                cumulative_run_delay_ns,
+                run_delay_ns: cumulative_run_delay_ns.saturating_sub(old.cumulative_run_delay_ns),
+                stat: old.stat,
+                name: stat.name,
+                state: stat.state,
+                ppid: stat.ppid,
+                sid: stat.sid,
+                tty_nr: stat.tty_nr,
+                num_threads: stat.num_threads,
+                start_time_ms: stat.start_time_ms,
+                cumulative_user_time_ms: stat.cumulative_user_time_ms,
+                cumulative_system_time_ms: stat.cumulative_system_time_ms,
+                cumulative_guest_time_ms: stat.cumulative_guest_time_ms,
+                cumulative_minor_faults: stat.cumulative_minor_faults,
+                cumulative_major_faults: stat.cumulative_major_faults,
+                user_time_ms: stat
+                    .cumulative_user_time_ms
+                    .saturating_sub(old.cumulative_user_time_ms)
+                    as u32,
+                system_time_ms: stat
+                    .cumulative_system_time_ms
+                    .saturating_sub(old.cumulative_system_time_ms)
+                    as u32,
+                guest_time_ms: stat
+                    .cumulative_guest_time_ms
+                    .saturating_sub(old.cumulative_guest_time_ms)
+                    as u32,
+                minor_faults: stat
+                    .cumulative_minor_faults
+                    .saturating_sub(old.cumulative_minor_faults),
+                major_faults: stat
+                    .cumulative_major_faults
+                    .saturating_sub(old.cumulative_major_faults),
+                nice: stat.nice,
+                rt_priority: stat.rt_priority,
+                policy: stat.policy,
+                io_priority,
+                affinity,
+                changed,
+            };
+            new.changed |= new.is_active();
+            ret.insert(tid, new);
         }
         Some(ret)
     }
+    /// Whether any rate since last update is nonzero.
+    fn is_active(&self) -> bool {
+        self.read_bytes != 0
+            || self.write_bytes != 0
+            || self.voluntary_switches != 0
+            || self.involuntary_switches != 0
+            || self.run_delay_ns != 0
+            || self.user_time_ms != 0
+            || self.system_time_ms != 0
+            || self.guest_time_ms != 0
+            || self.minor_faults != 0
+            || self.major_faults != 0
+    }
 }
@@ -1,6 +1,29 @@
+mod apps;
+mod cgroupfs;
+mod connector;
+mod container;
+mod control;
+mod fds;
+mod filter;
 mod info;
 mod ingest;
+mod netlink;
 mod procfs;
+mod sockdiag;
+mod users;
 
-pub use info::{ProcInfo, ProcSortBy, ProcStat};
-pub use ingest::ProcIngest;
+pub use container::{ContainerId, ContainerRuntime};
+pub use control::{
+    parse_cpu_list, send_signal, set_affinity, set_io_priority, set_nice, set_sched_policy,
+    IoClass, IoPriority, SchedPolicy,
+};
+pub use fds::{get_open_files, FdKind, OpenFile, SocketInfo};
+pub use filter::ProcFilter;
+pub use info::{
+    AppInfo, CgroupStat, ContainerInfo, ExitedProcessInfo, GroupUsage, Lsid, ProcInfo, ProcSortBy,
+    ProcStat, ProcTarget, ProcessHistory, ProcessInfo, SchedInfo, TaskInfo, ThreadInfo, UserUsage,
+};
+pub use ingest::{ProcIngest, ProcIngestOptions};
+pub use nix::{errno::Errno, sys::signal::Signal};
+pub use procfs::{get_memory_maps, rollup_memory_maps, MapRollup, MapUsage, MemoryMap, Namespaces};
+pub use users::{GroupInfo, UserInfo, UserKind};
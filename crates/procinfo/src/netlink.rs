@@ -0,0 +1,93 @@
+use std::{
+    io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+const NLMSG_HDRLEN: usize = 16;
+
+pub(crate) fn open(protocol: libc::c_int) -> io::Result<OwnedFd> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            protocol,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Binds to the multicast `groups`, a bitmask.
+pub(crate) fn bind(fd: &OwnedFd, groups: u32) -> io::Result<()> {
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = groups;
+    let ret = unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    };
+    match ret {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Sends `payload` to the kernel, prefixed by a `struct nlmsghdr`.
+pub(crate) fn send(fd: &OwnedFd, msg_type: u16, flags: u16, payload: &[u8]) -> io::Result<()> {
+    let len = NLMSG_HDRLEN + payload.len();
+    let mut msg = Vec::with_capacity(len);
+    msg.extend((len as u32).to_ne_bytes());
+    msg.extend(msg_type.to_ne_bytes());
+    msg.extend(flags.to_ne_bytes());
+    msg.extend(0u32.to_ne_bytes());
+    msg.extend(0u32.to_ne_bytes());
+    msg.extend(payload);
+    let ret = unsafe { libc::send(fd.as_raw_fd(), msg.as_ptr().cast(), msg.len(), 0) };
+    match ret < 0 {
+        true => Err(io::Error::last_os_error()),
+        false => Ok(()),
+    }
+}
+
+/// Blocks until a datagram arrives, returning the messages in it.
+pub(crate) fn recv<'a>(fd: &OwnedFd, buf: &'a mut [u8]) -> io::Result<Messages<'a>> {
+    let len = unsafe { libc::recv(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Messages {
+        buf: &buf[..len as usize],
+    })
+}
+
+/// The type and payload of each message in a datagram.
+pub(crate) struct Messages<'a> {
+    buf: &'a [u8],
+}
+impl<'a> Iterator for Messages<'a> {
+    type Item = (u16, &'a [u8]);
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = u32_at(self.buf, 0)? as usize;
+        let msg_type = u16::from_ne_bytes(self.buf.get(4..6)?.try_into().unwrap());
+        let payload = self.buf.get(NLMSG_HDRLEN..len)?;
+        // Messages are 4-byte aligned
+        self.buf = self.buf.get((len + 3) & !3..).unwrap_or_default();
+        Some((msg_type, payload))
+    }
+}
+
+pub(crate) fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_ne_bytes(
+        buf.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+pub(crate) fn u64_at(buf: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_ne_bytes(
+        buf.get(offset..offset + 8)?.try_into().unwrap(),
+    ))
+}
@@ -1,8 +1,12 @@
 use nix::errno::Errno;
 use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
     fs::{self, DirEntry, File},
-    io,
+    io::{self, Read},
+    os::unix::fs::OpenOptionsExt,
     path::Path,
+    time::Instant,
 };
 use util::TextualKeyValue;
 
@@ -23,6 +27,126 @@ pub fn get_is_kernel_name_cmdline(pid: u32) -> Option<(bool, String, Option<Stri
     }
 }
 
+/// The boot time, in seconds since the Unix epoch.
+pub fn get_boot_time() -> u64 {
+    // Unlike the files in `/proc/{pid}`, this is often larger than a page
+    let stat = fs::read_to_string("/proc/stat").unwrap();
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+/// Returns the audit session id and login uid, each `None` if unset, or if the kernel is built
+/// without audit support and so lacks the files.
+pub fn get_sessionid_loginuid(pid: u32) -> (Option<u32>, Option<u32>) {
+    const UNSET: u32 = u32::MAX;
+    let read = |file: &str| {
+        let id: u32 = read_to_string(format!("/proc/{pid}/{file}"))?
+            .trim()
+            .parse()
+            .unwrap();
+        Some(id).filter(|&id| id != UNSET)
+    };
+    (read("sessionid"), read("loginuid"))
+}
+
+/// `/run/systemd/sessions/{id}`, written by `systemd-logind`.
+#[derive(Debug, Default)]
+pub struct SystemdSession {
+    pub user: Option<String>,
+    pub seat: Option<String>,
+    pub tty: Option<String>,
+}
+impl SystemdSession {
+    pub fn new(id: u32) -> Option<Self> {
+        let data = match fs::read_to_string(format!("/run/systemd/sessions/{id}")) {
+            Ok(data) => data,
+            Err(err) => {
+                check_io_err(err);
+                return None;
+            }
+        };
+        let mut ret = Self::default();
+        for line in data.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let slot = match key {
+                "USER" => &mut ret.user,
+                "SEAT" => &mut ret.seat,
+                "TTY" => &mut ret.tty,
+                _ => continue,
+            };
+            *slot = Some(value.to_owned());
+        }
+        Some(ret)
+    }
+}
+
+/// Namespace inode numbers, as in the links of `/proc/{pid}/ns`. Processes in the same
+/// namespace see the same inode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Namespaces {
+    pub pid: u64,
+    pub net: u64,
+    pub mnt: u64,
+    pub user: u64,
+    pub uts: u64,
+}
+/// Returns `None` if the process exited, or if we lack `PTRACE_MODE_READ_FSCREDS` for it.
+pub fn get_namespaces(pid: u32) -> Option<Namespaces> {
+    read_namespaces(&pid.to_string())
+}
+pub fn get_own_namespaces() -> Option<Namespaces> {
+    read_namespaces("self")
+}
+fn read_namespaces(dir: &str) -> Option<Namespaces> {
+    let inode = |kind: &str| -> Option<u64> {
+        let target = fs::read_link(format!("/proc/{dir}/ns/{kind}"))
+            .map_err(check_io_err)
+            .ok()?;
+        // Like `pid:[4026531836]`
+        let target = target.to_str().unwrap();
+        Some(
+            target
+                .strip_prefix(kind)
+                .and_then(|s| s.strip_prefix(":["))
+                .and_then(|s| s.strip_suffix(']'))
+                .unwrap()
+                .parse()
+                .unwrap(),
+        )
+    };
+    Some(Namespaces {
+        pid: inode("pid")?,
+        net: inode("net")?,
+        mnt: inode("mnt")?,
+        user: inode("user")?,
+        uts: inode("uts")?,
+    })
+}
+/// As configured in the root filesystem of the process, so usually only meaningful for the
+/// first process of a container. Needs the same privileges as reading its namespaces.
+pub fn get_hostname(pid: u32) -> Option<String> {
+    // The container controls the file, which could be a symlink into our own filesystem, a
+    // FIFO that never yields data, or endless
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(format!("/proc/{pid}/root/etc/hostname"))
+        .ok()?;
+    if !file.metadata().ok()?.is_file() {
+        return None;
+    }
+    // At most 64 bytes, as limited by the kernel
+    let mut hostname = Vec::new();
+    file.take(256).read_to_end(&mut hostname).ok()?;
+    let hostname = String::from_utf8_lossy(&hostname);
+    Some(hostname.trim().to_owned()).filter(|hostname| !hostname.is_empty())
+}
+
 pub fn get_live_tids(pid: u32) -> impl Iterator<Item = u32> {
     read_dir(format!("/proc/{pid}/task")).map(|entry| direntry_as_u32(entry).unwrap())
 }
@@ -43,11 +167,16 @@ impl PidStatus {
             is_kernel,
         })
     }
-    pub fn get_uid_gid_vm_rss_kb_threads(&mut self) -> Option<(u16, u16, u64, u32)> {
+    /// The pid within the innermost pid namespace is `None` if that is ours.
+    pub fn get_uid_gid_nspid_mem_threads(
+        &mut self,
+    ) -> Option<(u32, u32, Option<u32>, StatusMemKb, u32)> {
         let mut uid = 0;
         let mut gid = 0;
-        let mut vm_rss_kb = 0;
+        let mut mem = StatusMemKb::default();
         let mut threads = 0;
+        let mut buf = [0u8; 4096];
+        let data = read_file_to_string(&mut self.file, &mut buf)?;
         TextualKeyValue::extract_from(
             &mut [
                 Some(TextualKeyValue {
@@ -58,18 +187,406 @@ impl PidStatus {
                     key: "Gid",
                     value: &mut gid,
                 }),
+                (!self.is_kernel).then_some(TextualKeyValue {
+                    key: "VmHWM",
+                    value: &mut mem.vm_hwm,
+                }),
                 (!self.is_kernel).then_some(TextualKeyValue {
                     key: "VmRSS",
-                    value: &mut vm_rss_kb,
+                    value: &mut mem.vm_rss,
+                }),
+                (!self.is_kernel).then_some(TextualKeyValue {
+                    key: "RssAnon",
+                    value: &mut mem.rss_anon,
+                }),
+                (!self.is_kernel).then_some(TextualKeyValue {
+                    key: "RssFile",
+                    value: &mut mem.rss_file,
+                }),
+                (!self.is_kernel).then_some(TextualKeyValue {
+                    key: "RssShmem",
+                    value: &mut mem.rss_shmem,
+                }),
+                (!self.is_kernel).then_some(TextualKeyValue {
+                    key: "VmSwap",
+                    value: &mut mem.vm_swap,
                 }),
                 Some(TextualKeyValue {
                     key: "Threads",
                     value: &mut threads,
                 }),
             ],
-            &read_file_to_string(&mut self.file, &mut [0u8; 4096])?,
+            data,
         )?;
-        Some((uid as u16, gid as u16, vm_rss_kb, threads as u32))
+        // Our pid, followed by that in each nested pid namespace. Missing before Linux 4.1.
+        let ns_pid = data
+            .lines()
+            .find_map(|line| line.strip_prefix("NSpid:"))
+            .and_then(|pids| {
+                let mut pids = pids.split_ascii_whitespace();
+                pids.next()?;
+                pids.last()?.parse().ok()
+            });
+        Some((uid as u32, gid as u32, ns_pid, mem, threads as u32))
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatusMemKb {
+    /// The peak resident set size.
+    pub vm_hwm: u64,
+    pub vm_rss: u64,
+    pub rss_anon: u64,
+    pub rss_file: u64,
+    pub rss_shmem: u64,
+    pub vm_swap: u64,
+}
+
+/// Walks the page tables of the process on every read, so it is opt-in.
+pub struct PidSmapsRollup {
+    file: File,
+}
+impl PidSmapsRollup {
+    pub fn new(pid: u32) -> Option<Self> {
+        let mut file = match File::open(format!("/proc/{pid}/smaps_rollup")) {
+            Ok(file) => file,
+            Err(err) => {
+                check_io_err(err);
+                return None;
+            }
+        };
+        can_read_from(&mut file).then_some(Self { file })
+    }
+    pub fn get_pss_uss_kb(&mut self) -> Option<(u64, u64)> {
+        let mut pss = 0;
+        let mut private_clean = 0;
+        let mut private_dirty = 0;
+        TextualKeyValue::extract_from(
+            &mut [
+                Some(TextualKeyValue {
+                    key: "Pss",
+                    value: &mut pss,
+                }),
+                Some(TextualKeyValue {
+                    key: "Private_Clean",
+                    value: &mut private_clean,
+                }),
+                Some(TextualKeyValue {
+                    key: "Private_Dirty",
+                    value: &mut private_dirty,
+                }),
+            ],
+            read_file_to_string(&mut self.file, &mut [0u8; 4096])?,
+        )?;
+        Some((pss, private_clean + private_dirty))
+    }
+}
+
+/// A line of `/proc/{pid}/maps`, along with its `smaps` entry if requested.
+#[derive(Debug, Clone)]
+pub struct MemoryMap {
+    pub start: u64,
+    pub end: u64,
+    /// Like `r-xp`, where the last letter is `p` for private or `s` for shared.
+    pub perms: String,
+    pub offset: u64,
+    /// The backing file, a pseudo-path like `[heap]`, or empty for anonymous mappings.
+    pub path: String,
+    pub usage: Option<MapUsage>,
+}
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MapUsage {
+    pub rss_bytes: u64,
+    pub pss_bytes: u64,
+    /// Both shared and private dirty pages.
+    pub dirty_bytes: u64,
+    pub swap_bytes: u64,
+}
+/// All mappings of the same backing file.
+#[derive(Debug, Clone)]
+pub struct MapRollup {
+    pub path: String,
+    pub mappings: usize,
+    pub size_bytes: u64,
+    pub usage: Option<MapUsage>,
+}
+
+/// Reads `/proc/{pid}/smaps` if `smaps`, and otherwise the much cheaper `/proc/{pid}/maps`.
+/// Returns `None` if the process exited or we lack `PTRACE_MODE_READ_FSCREDS`.
+pub fn get_memory_maps(pid: u32, smaps: bool) -> Option<Vec<MemoryMap>> {
+    let file = if smaps { "smaps" } else { "maps" };
+    // Usually far larger than a page, and with paths that may be any bytes
+    let data = match fs::read(format!("/proc/{pid}/{file}")) {
+        Ok(data) => data,
+        Err(err) => {
+            check_io_err(err);
+            return None;
+        }
+    };
+    let data = String::from_utf8_lossy(&data);
+    let mut ret: Vec<MemoryMap> = Vec::new();
+    for line in data.lines() {
+        let mut fields = line.split_ascii_whitespace();
+        let first = fields.next().unwrap();
+        if let Some(key) = first.strip_suffix(':') {
+            // smaps `Key: value kB` line of the latest mapping
+            let usage = ret
+                .last_mut()
+                .unwrap()
+                .usage
+                .get_or_insert_with(Default::default);
+            let slot = match key {
+                "Rss" => &mut usage.rss_bytes,
+                "Pss" => &mut usage.pss_bytes,
+                "Shared_Dirty" | "Private_Dirty" => &mut usage.dirty_bytes,
+                "Swap" => &mut usage.swap_bytes,
+                _ => continue,
+            };
+            *slot += 1024 * fields.next().unwrap().parse::<u64>().unwrap();
+            continue;
+        }
+        // start-end perms offset dev inode path
+        let (start, end) = first.split_once('-').unwrap();
+        let perms = fields.next().unwrap().to_owned();
+        let offset = u64::from_str_radix(fields.next().unwrap(), 16).unwrap();
+        let path = fields.nth(2).map_or_else(String::new, |path_start| {
+            // The path may contain spaces
+            let idx = path_start.as_ptr() as usize - line.as_ptr() as usize;
+            line[idx..].to_owned()
+        });
+        ret.push(MemoryMap {
+            start: u64::from_str_radix(start, 16).unwrap(),
+            end: u64::from_str_radix(end, 16).unwrap(),
+            perms,
+            offset,
+            path,
+            usage: smaps.then(MapUsage::default),
+        });
+    }
+    Some(ret)
+}
+
+/// Sums the mappings of every backing file, largest PSS (or size, without `smaps`) first.
+pub fn rollup_memory_maps(maps: &[MemoryMap]) -> Vec<MapRollup> {
+    let mut by_path: BTreeMap<&str, MapRollup> = BTreeMap::new();
+    for map in maps {
+        let rollup = by_path.entry(&map.path).or_insert_with(|| MapRollup {
+            path: map.path.clone(),
+            mappings: 0,
+            size_bytes: 0,
+            usage: map.usage.map(|_| MapUsage::default()),
+        });
+        rollup.mappings += 1;
+        rollup.size_bytes += map.end - map.start;
+        if let (Some(sum), Some(usage)) = (rollup.usage.as_mut(), map.usage) {
+            sum.rss_bytes += usage.rss_bytes;
+            sum.pss_bytes += usage.pss_bytes;
+            sum.dirty_bytes += usage.dirty_bytes;
+            sum.swap_bytes += usage.swap_bytes;
+        }
+    }
+    let mut ret: Vec<MapRollup> = by_path.into_values().collect();
+    ret.sort_by_key(|rollup| {
+        Reverse(
+            rollup
+                .usage
+                .map_or(rollup.size_bytes, |usage| usage.pss_bytes),
+        )
+    });
+    ret
+}
+
+/// A DRM client, which every file descriptor of an open GPU device shares, even across `fork`.
+#[derive(Debug, Clone)]
+pub struct DrmClient {
+    /// The PCI address of the GPU, such as `0000:03:00.0`.
+    pub pdev: String,
+    pub client_id: u64,
+    /// Summed over engines, such as `render` and `video`, so it can grow faster than real time.
+    pub cumulative_engine_ns: u64,
+    /// From drivers like xe that count GPU clock cycles instead of time: the busy and total
+    /// cycles of each engine class, such as `rcs` and `vcs`.
+    pub engine_cycles: Vec<(String, u64, u64)>,
+    /// When `fdinfo` was read, to turn `engine_cycles` into time.
+    pub read_at: Instant,
+    /// Resident in device memory. Zero on integrated GPUs, which only use system memory.
+    pub vram_bytes: u64,
+}
+
+/// What the file descriptors of a process refer to, for what is accounted per open file.
+#[derive(Debug, Default)]
+pub struct FdUsage {
+    /// Of `/dev/dri` file descriptors, as published in `/proc/{pid}/fdinfo` by the amdgpu, i915,
+    /// xe and nouveau drivers among others.
+    pub drm_clients: Vec<DrmClient>,
+    pub socket_inodes: Vec<u64>,
+}
+
+/// Returns `None` if the process exited, or if we lack `PTRACE_MODE_READ_FSCREDS` for it.
+pub fn get_fd_usage(pid: u32) -> Option<FdUsage> {
+    let dir = fs::read_dir(format!("/proc/{pid}/fd"))
+        .map_err(check_io_err)
+        .ok()?;
+    let mut ret = FdUsage::default();
+    for entry in dir {
+        // Entries disappear as the process closes them
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let Ok(target) = fs::read_link(entry.path()) else {
+            continue;
+        };
+        let target = target.to_string_lossy();
+        if let Some(inode) = target
+            .strip_prefix("socket:[")
+            .and_then(|s| s.strip_suffix(']'))
+        {
+            ret.socket_inodes.push(inode.parse().unwrap());
+            continue;
+        }
+        if !target.starts_with("/dev/dri/") {
+            continue;
+        }
+        let fd = entry.file_name();
+        let Some(client) = read_to_string(format!("/proc/{pid}/fdinfo/{}", fd.to_str().unwrap()))
+            .and_then(|fdinfo| DrmClient::parse(&fdinfo))
+        else {
+            continue;
+        };
+        if !ret
+            .drm_clients
+            .iter()
+            .any(|c| c.client_id == client.client_id && c.pdev == client.pdev)
+        {
+            ret.drm_clients.push(client);
+        }
+    }
+    // Duplicated file descriptors share the socket
+    ret.socket_inodes.sort_unstable();
+    ret.socket_inodes.dedup();
+    Some(ret)
+}
+impl DrmClient {
+    /// Returns `None` for drivers predating `drm-client-id`.
+    fn parse(fdinfo: &str) -> Option<Self> {
+        let mut pdev = String::new();
+        let mut client_id = None;
+        let mut cumulative_engine_ns = 0;
+        let mut engine_cycles: Vec<(String, u64, u64)> = Vec::new();
+        // Older kernels only have `drm-memory-*`, newer ones also `drm-resident-*`
+        let mut memory_vram_bytes = 0;
+        let mut resident_vram_bytes = None;
+        for line in fdinfo.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if key == "drm-pdev" {
+                pdev = value.to_owned();
+            } else if key == "drm-client-id" {
+                client_id = value.parse().ok();
+            } else if let Some(engine) = key.strip_prefix("drm-engine-") {
+                // Also `drm-engine-capacity-*`, which counts engines rather than time
+                if let (false, Some(ns)) = (
+                    engine.starts_with("capacity-"),
+                    value
+                        .strip_suffix(" ns")
+                        .and_then(|ns| ns.parse::<u64>().ok()),
+                ) {
+                    cumulative_engine_ns += ns;
+                }
+            } else if let Some(engine) = key.strip_prefix("drm-cycles-") {
+                if let Ok(cycles) = value.parse() {
+                    match engine_cycles.iter_mut().find(|(e, _, _)| e == engine) {
+                        Some((_, busy, _)) => *busy = cycles,
+                        None => engine_cycles.push((engine.to_owned(), cycles, 0)),
+                    }
+                }
+            } else if let Some(engine) = key.strip_prefix("drm-total-cycles-") {
+                if let Ok(cycles) = value.parse() {
+                    match engine_cycles.iter_mut().find(|(e, _, _)| e == engine) {
+                        Some((_, _, total)) => *total = cycles,
+                        None => engine_cycles.push((engine.to_owned(), 0, cycles)),
+                    }
+                }
+            } else if let Some(region) = key.strip_prefix("drm-memory-") {
+                if is_vram_region(region) {
+                    memory_vram_bytes += parse_drm_size(value).unwrap_or(0);
+                }
+            } else if let Some(region) = key.strip_prefix("drm-resident-") {
+                if is_vram_region(region) {
+                    *resident_vram_bytes.get_or_insert(0) += parse_drm_size(value).unwrap_or(0);
+                }
+            }
+        }
+        Some(Self {
+            pdev,
+            client_id: client_id?,
+            cumulative_engine_ns,
+            engine_cycles,
+            read_at: Instant::now(),
+            vram_bytes: resident_vram_bytes.unwrap_or(memory_vram_bytes),
+        })
+    }
+    /// Engine time since `old` was read. Cycles are converted by the fraction of the total
+    /// cycles each engine was busy, as the clock they count differs between GPUs.
+    pub fn engine_ns_since(&self, old: Option<&Self>) -> u64 {
+        let Some(old) = old else {
+            return self.cumulative_engine_ns;
+        };
+        let elapsed_ns = self.read_at.duration_since(old.read_at).as_nanos() as f64;
+        let cycles_ns: f64 = self
+            .engine_cycles
+            .iter()
+            .filter_map(|(engine, busy, total)| {
+                let (_, old_busy, old_total) =
+                    old.engine_cycles.iter().find(|(e, _, _)| e == engine)?;
+                let total = total.checked_sub(*old_total).filter(|&total| total > 0)?;
+                let busy = busy.saturating_sub(*old_busy).min(total);
+                Some(busy as f64 / total as f64 * elapsed_ns)
+            })
+            .sum();
+        self.cumulative_engine_ns
+            .saturating_sub(old.cumulative_engine_ns)
+            + cycles_ns as u64
+    }
+}
+/// `vram` on amdgpu, nouveau and xe, and `local0` and so on for i915.
+fn is_vram_region(region: &str) -> bool {
+    region.starts_with("vram") || region.starts_with("local")
+}
+/// Parses a size like `1024 KiB` into bytes.
+fn parse_drm_size(value: &str) -> Option<u64> {
+    let (number, unit) = value.split_once(' ').unwrap_or((value, ""));
+    let scale = match unit {
+        "" => 1,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        _ => return None,
+    };
+    Some(number.parse::<u64>().ok()? * scale)
+}
+
+pub struct PidCgroup {
+    file: File,
+}
+impl PidCgroup {
+    pub fn new(pid: u32) -> Option<Self> {
+        Some(Self {
+            file: File::open(format!("/proc/{pid}/cgroup"))
+                .map_err(check_io_err)
+                .ok()?,
+        })
+    }
+    /// The cgroup v2 path, or `Some(None)` if the process is only in v1 hierarchies.
+    pub fn get_path(&mut self) -> Option<Option<String>> {
+        let buf = &mut [0u8; 4096];
+        let data = read_file_to_string(&mut self.file, buf)?;
+        Some(
+            data.lines()
+                .find_map(|line| line.strip_prefix("0::"))
+                .map(str::to_owned),
+        )
     }
 }
 
@@ -78,7 +595,14 @@ pub struct TidIo {
 }
 impl TidIo {
     pub fn new(pid: u32, tid: u32) -> Option<Self> {
-        let mut file = match File::open(format!("/proc/{pid}/task/{tid}/io")) {
+        Self::open(format!("/proc/{pid}/task/{tid}/io"))
+    }
+    /// `/proc/{pid}/io`, which also counts threads that have exited.
+    pub fn new_process(pid: u32) -> Option<Self> {
+        Self::open(format!("/proc/{pid}/io"))
+    }
+    fn open(path: String) -> Option<Self> {
+        let mut file = match File::open(path) {
             Ok(file) => file,
             Err(err) => {
                 check_io_err(err);
@@ -107,13 +631,74 @@ impl TidIo {
     }
 }
 
+pub struct TidStatus {
+    file: File,
+}
+impl TidStatus {
+    pub fn new(pid: u32, tid: u32) -> Option<Self> {
+        Some(Self {
+            file: File::open(format!("/proc/{pid}/task/{tid}/status"))
+                .map_err(check_io_err)
+                .ok()?,
+        })
+    }
+    /// Returns the voluntary and involuntary context switches.
+    pub fn get_cumulative_context_switches(&mut self) -> Option<(u64, u64)> {
+        let mut voluntary = 0;
+        let mut involuntary = 0;
+        TextualKeyValue::extract_from(
+            &mut [
+                Some(TextualKeyValue {
+                    key: "voluntary_ctxt_switches",
+                    value: &mut voluntary,
+                }),
+                Some(TextualKeyValue {
+                    key: "nonvoluntary_ctxt_switches",
+                    value: &mut involuntary,
+                }),
+            ],
+            read_file_to_string(&mut self.file, &mut [0u8; 4096])?,
+        )?;
+        Some((voluntary, involuntary))
+    }
+}
+
+/// Missing unless the kernel has `CONFIG_SCHED_INFO`.
+pub struct TidSchedstat {
+    file: File,
+}
+impl TidSchedstat {
+    pub fn new(pid: u32, tid: u32) -> Option<Self> {
+        Some(Self {
+            file: File::open(format!("/proc/{pid}/task/{tid}/schedstat"))
+                .map_err(check_io_err)
+                .ok()?,
+        })
+    }
+    /// Returns the time spent waiting on a run queue, in nanoseconds.
+    pub fn get_cumulative_run_delay_ns(&mut self) -> Option<u64> {
+        // run_ns wait_ns timeslices
+        let buf = &mut [0u8; 4096];
+        let data = read_file_to_string(&mut self.file, buf)?;
+        Some(data.split(' ').nth(1).unwrap().parse().unwrap())
+    }
+}
+
 pub struct TidStat {
     file: File,
 }
 impl TidStat {
     pub fn new(pid: u32, tid: u32) -> Option<Self> {
+        Self::open(format!("/proc/{pid}/task/{tid}/stat"))
+    }
+    /// `/proc/{pid}/stat`, in the same format but with the times of the whole process, including
+    /// those of threads that have exited.
+    pub fn new_process(pid: u32) -> Option<Self> {
+        Self::open(format!("/proc/{pid}/stat"))
+    }
+    fn open(path: String) -> Option<Self> {
         Some(Self {
-            file: match File::open(format!("/proc/{pid}/task/{tid}/stat")) {
+            file: match File::open(path) {
                 Ok(file) => file,
                 Err(err) => {
                     check_io_err(err);
@@ -122,22 +707,81 @@ impl TidStat {
             },
         })
     }
-    pub fn get_sid_cumulative_user_system_guest_time(&mut self) -> Option<(u32, u64, u64, u64)> {
+    pub fn get_fields(&mut self) -> Option<TidStatFields> {
         let buf = &mut [0u8; 4096];
         let stat_data = read_file_to_string(&mut self.file, buf)?;
-        let mut stat_entries = stat_data.split(' ');
-        let sid = stat_entries.nth(5).unwrap().parse().unwrap();
-        let cumulative_user_time_ms = stat_entries.nth(7).unwrap().parse::<u64>().unwrap() * 10;
+        // The name may contain spaces and parentheses, so start after its last closing paren.
+        let name_end = stat_data.rfind(')').unwrap();
+        let name = stat_data[stat_data.find('(').unwrap() + 1..name_end].to_owned();
+        let after_name = &stat_data[name_end + 2..];
+        let mut stat_entries = after_name.split(' ');
+        let state = stat_entries.next().unwrap().chars().next().unwrap();
+        let ppid = stat_entries.next().unwrap().parse().unwrap();
+        let sid = stat_entries.nth(1).unwrap().parse().unwrap();
+        let tty_nr = stat_entries.next().unwrap().parse().unwrap();
+        let cumulative_minor_faults = stat_entries.nth(2).unwrap().parse().unwrap();
+        let cumulative_major_faults = stat_entries.nth(1).unwrap().parse().unwrap();
+        let cumulative_user_time_ms = stat_entries.nth(1).unwrap().parse::<u64>().unwrap() * 10;
         let cumulative_system_time_ms = stat_entries.nth(0).unwrap().parse::<u64>().unwrap() * 10;
-        let cumulative_guest_time_ms = stat_entries.nth(28).unwrap().parse::<u64>().unwrap() * 10;
-        Some((
+        let cumulative_children_user_time_ms =
+            stat_entries.nth(0).unwrap().parse::<u64>().unwrap() * 10;
+        let cumulative_children_system_time_ms =
+            stat_entries.nth(0).unwrap().parse::<u64>().unwrap() * 10;
+        let nice = stat_entries.nth(1).unwrap().parse().unwrap();
+        let num_threads = stat_entries.next().unwrap().parse().unwrap();
+        let start_time_ms = stat_entries.nth(1).unwrap().parse::<u64>().unwrap() * 10;
+        let rt_priority = stat_entries.nth(17).unwrap().parse().unwrap();
+        let policy = stat_entries.nth(0).unwrap().parse().unwrap();
+        let cumulative_guest_time_ms = stat_entries.nth(1).unwrap().parse::<u64>().unwrap() * 10;
+        Some(TidStatFields {
+            name,
+            state,
+            ppid,
             sid,
+            tty_nr,
+            cumulative_minor_faults,
+            cumulative_major_faults,
             cumulative_user_time_ms,
             cumulative_system_time_ms,
             cumulative_guest_time_ms,
-        ))
+            cumulative_children_user_time_ms,
+            cumulative_children_system_time_ms,
+            nice,
+            num_threads,
+            start_time_ms,
+            rt_priority,
+            policy,
+        })
     }
 }
+/// The fields of `/proc/{pid}/task/{tid}/stat` that we use.
+#[derive(Clone, Debug)]
+pub struct TidStatFields {
+    /// The thread name, as in `/proc/{pid}/task/{tid}/comm` and set by `prctl(PR_SET_NAME)`.
+    pub name: String,
+    /// Such as `R` for running or `D` for uninterruptible sleep.
+    pub state: char,
+    pub ppid: u32,
+    pub sid: u32,
+    /// The device number of the controlling terminal, or zero.
+    pub tty_nr: u32,
+    pub cumulative_minor_faults: u64,
+    /// Faults that required reading from disk.
+    pub cumulative_major_faults: u64,
+    pub cumulative_user_time_ms: u64,
+    pub cumulative_system_time_ms: u64,
+    pub cumulative_guest_time_ms: u64,
+    /// Of reaped children of the whole process, including their own reaped children.
+    pub cumulative_children_user_time_ms: u64,
+    pub cumulative_children_system_time_ms: u64,
+    pub nice: i8,
+    /// Of the whole process.
+    pub num_threads: u32,
+    /// Since boot.
+    pub start_time_ms: u64,
+    pub rt_priority: u8,
+    pub policy: u32,
+}
 
 fn direntry_as_u32(entry: DirEntry) -> Option<u32> {
     entry.file_name().to_str().unwrap().parse::<u32>().ok()
@@ -149,20 +793,38 @@ fn read_to_string(path: impl AsRef<Path>) -> Option<String> {
     let mut file = File::open(path).ok()?;
     Some(read_file_to_string(&mut file, &mut [0u8; 4096])?.to_owned())
 }
-fn read_file_to_string<'a>(file: &mut File, buf: &'a mut [u8; 4096]) -> Option<&'a str> {
+pub(crate) fn read_file_to_string<'a>(file: &mut File, buf: &'a mut [u8; 4096]) -> Option<&'a str> {
     // For performance reasons, we assume that read returns the entire (tiny, <4K) file.
     match nix::sys::uio::pread(file, buf, 0) {
         Ok(len) => Some(std::str::from_utf8(&buf[..len]).unwrap()),
-        Err(Errno::ENOENT | Errno::ESRCH | Errno::EACCES) => None,
+        // ENODEV when reading a file of a removed cgroup
+        Err(Errno::ENOENT | Errno::ESRCH | Errno::EACCES | Errno::ENODEV) => None,
         Err(other) => panic!("{other}"),
     }
 }
+/// Like [`read_file_to_string`], for files that may be larger than a page.
+pub(crate) fn read_file_to_end<'a>(file: &mut File, buf: &'a mut Vec<u8>) -> Option<&'a str> {
+    buf.clear();
+    loop {
+        let len = buf.len();
+        buf.resize(len + 4096, 0);
+        match nix::sys::uio::pread(&*file, &mut buf[len..], len as i64) {
+            Ok(0) => {
+                buf.truncate(len);
+                return Some(std::str::from_utf8(buf).unwrap());
+            }
+            Ok(read) => buf.truncate(len + read),
+            Err(Errno::ENOENT | Errno::ESRCH | Errno::EACCES | Errno::ENODEV) => return None,
+            Err(other) => panic!("{other}"),
+        }
+    }
+}
 fn can_read_from(file: &mut File) -> bool {
     read_file_to_string(file, &mut [0u8; 4096]).is_some()
 }
 
 #[track_caller]
-fn check_io_err(err: io::Error) {
+pub(crate) fn check_io_err(err: io::Error) {
     if err.kind() == io::ErrorKind::NotFound || err.kind() == io::ErrorKind::PermissionDenied {
         return;
     }
@@ -0,0 +1,97 @@
+use crate::netlink::{self, u32_at, u64_at};
+use std::{collections::HashMap, io, os::fd::OwnedFd};
+
+// From `linux/sock_diag.h`, `linux/inet_diag.h` and `linux/tcp.h`
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const INET_DIAG_INFO: u16 = 2;
+const INET_DIAG_REQ_V2_LEN: usize = 56;
+const INET_DIAG_MSG_LEN: usize = 72;
+/// The offset of `idiag_inode` within `struct inet_diag_msg`.
+const INODE_OFFSET: usize = 68;
+/// The offsets of `tcpi_bytes_acked` and `tcpi_bytes_received` within `struct tcp_info`.
+const BYTES_ACKED_OFFSET: usize = 120;
+const BYTES_RECEIVED_OFFSET: usize = 128;
+
+/// Cumulative payload bytes of a TCP socket. Sent bytes count once acknowledged, so
+/// retransmissions are not counted again.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TcpBytes {
+    pub sent: u64,
+    pub received: u64,
+}
+
+/// A `NETLINK_SOCK_DIAG` socket. Unprivileged, but only sees our own network namespace.
+pub(crate) struct SockDiag {
+    fd: OwnedFd,
+}
+
+impl SockDiag {
+    pub(crate) fn open() -> io::Result<Self> {
+        Ok(Self {
+            fd: netlink::open(libc::NETLINK_SOCK_DIAG)?,
+        })
+    }
+
+    /// TCP sockets over IPv4 and IPv6, by inode.
+    pub(crate) fn get_tcp_bytes_by_inode(&mut self) -> HashMap<u64, TcpBytes> {
+        let mut ret = HashMap::new();
+        for family in [libc::AF_INET, libc::AF_INET6] {
+            if let Err(err) = self.dump_tcp(family as u8, &mut ret) {
+                tracing::warn!("dumping TCP sockets failed: {err}");
+            }
+        }
+        ret
+    }
+
+    fn dump_tcp(&mut self, family: u8, ret: &mut HashMap<u64, TcpBytes>) -> io::Result<()> {
+        // A `struct inet_diag_req_v2` matching every socket in any state
+        let mut req = [0u8; INET_DIAG_REQ_V2_LEN];
+        req[0] = family;
+        req[1] = libc::IPPROTO_TCP as u8;
+        req[2] = 1 << (INET_DIAG_INFO - 1);
+        req[4..8].copy_from_slice(&u32::MAX.to_ne_bytes());
+        netlink::send(
+            &self.fd,
+            SOCK_DIAG_BY_FAMILY,
+            (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16,
+            &req,
+        )?;
+
+        let mut buf = vec![0u8; 32 << 10];
+        loop {
+            for (msg_type, msg) in netlink::recv(&self.fd, &mut buf)? {
+                match msg_type as libc::c_int {
+                    libc::NLMSG_DONE => return Ok(()),
+                    libc::NLMSG_ERROR => {
+                        let errno = u32_at(msg, 0).unwrap_or(0) as i32;
+                        return Err(io::Error::from_raw_os_error(-errno));
+                    }
+                    _ => {}
+                }
+                // Such as for sockets in `TIME_WAIT`, which no process owns
+                let Some(inode) = u32_at(msg, INODE_OFFSET).filter(|&inode| inode != 0) else {
+                    continue;
+                };
+                // Followed by attributes, each a 4-byte aligned `struct rtattr` and its data
+                let mut attrs = msg.get(INET_DIAG_MSG_LEN..).unwrap_or_default();
+                while let Some(header) = attrs.get(..4) {
+                    let len = u16::from_ne_bytes([header[0], header[1]]) as usize;
+                    let attr_type = u16::from_ne_bytes([header[2], header[3]]);
+                    let Some(data) = attrs.get(4..len) else {
+                        break;
+                    };
+                    if attr_type == INET_DIAG_INFO {
+                        // Missing on kernels before 4.2
+                        if let (Some(sent), Some(received)) = (
+                            u64_at(data, BYTES_ACKED_OFFSET),
+                            u64_at(data, BYTES_RECEIVED_OFFSET),
+                        ) {
+                            ret.insert(inode as u64, TcpBytes { sent, received });
+                        }
+                    }
+                    attrs = attrs.get((len + 3) & !3..).unwrap_or_default();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{c_char, CStr},
+    fmt, io, mem, ptr,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// Entries larger than this are treated as missing rather than growing the buffer further.
+const MAX_ENTRY_LEN: usize = 1 << 20;
+
+#[derive(Debug)]
+pub struct UserInfo {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub description: String,
+    pub kind: UserKind,
+}
+#[derive(Clone, Copy, Debug)]
+pub enum UserKind {
+    Root,
+    User,
+    /// A system account with a `nologin` shell.
+    Nologin,
+}
+impl fmt::Display for UserKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Root => "root",
+            Self::User => "user",
+            Self::Nologin => "nologin",
+        })
+    }
+}
+#[derive(Debug)]
+pub struct GroupInfo {
+    pub name: String,
+    pub gid: u32,
+    pub users: Vec<String>,
+}
+
+/// Users and groups looked up through NSS on first use, so that LDAP, SSSD and
+/// systemd-homed accounts are found as well as those in `/etc/passwd`. Ids without an entry
+/// are remembered as such, and not looked up again.
+///
+/// Lookups run on a thread of their own, as a slow directory server would otherwise hold up
+/// updates, and with them the UI waiting on `ProcInfo`. Entries arrive through `receive`.
+#[derive(Debug, Default)]
+pub(crate) struct UserCache {
+    users: HashMap<u32, Option<UserInfo>>,
+    groups: HashMap<u32, Option<GroupInfo>>,
+    /// Sent to `resolver`, with no entry received yet.
+    pending: HashSet<Lookup>,
+    /// Started on the first lookup.
+    resolver: Option<Resolver>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Lookup {
+    User(u32),
+    Group(u32),
+}
+#[derive(Debug)]
+enum Entry {
+    User(u32, Option<UserInfo>),
+    Group(u32, Option<GroupInfo>),
+}
+#[derive(Debug)]
+struct Resolver {
+    lookups: Sender<Lookup>,
+    entries: Receiver<Entry>,
+}
+impl UserCache {
+    pub(crate) fn resolve_user(&mut self, uid: u32) {
+        if !self.users.contains_key(&uid) {
+            self.lookup(Lookup::User(uid));
+        }
+    }
+    pub(crate) fn resolve_group(&mut self, gid: u32) {
+        if !self.groups.contains_key(&gid) {
+            self.lookup(Lookup::Group(gid));
+        }
+    }
+    fn lookup(&mut self, lookup: Lookup) {
+        if !self.pending.insert(lookup) {
+            return;
+        }
+        let resolver = self.resolver.get_or_insert_with(Resolver::start);
+        // Should the thread have panicked, the id is shown as a number
+        let _ = resolver.lookups.send(lookup);
+    }
+    /// Stores the entries looked up since the last call, without waiting for those pending.
+    /// Returns whether any arrived.
+    pub(crate) fn receive(&mut self) -> bool {
+        let Some(resolver) = &self.resolver else {
+            return false;
+        };
+        let mut received = false;
+        for entry in resolver.entries.try_iter() {
+            received = true;
+            match entry {
+                Entry::User(uid, user) => {
+                    self.pending.remove(&Lookup::User(uid));
+                    self.users.insert(uid, user);
+                }
+                Entry::Group(gid, group) => {
+                    self.pending.remove(&Lookup::Group(gid));
+                    self.groups.insert(gid, group);
+                }
+            }
+        }
+        received
+    }
+    pub(crate) fn user(&self, uid: u32) -> Option<&UserInfo> {
+        self.users.get(&uid)?.as_ref()
+    }
+    pub(crate) fn group(&self, gid: u32) -> Option<&GroupInfo> {
+        self.groups.get(&gid)?.as_ref()
+    }
+    /// The name of `uid`, or the number itself if it has no entry or it is not yet known.
+    pub(crate) fn user_name(&self, uid: u32) -> String {
+        self.user(uid)
+            .map_or_else(|| uid.to_string(), |user| user.name.clone())
+    }
+    /// The name of `gid`, or the number itself if it has no entry or it is not yet known.
+    pub(crate) fn group_name(&self, gid: u32) -> String {
+        self.group(gid)
+            .map_or_else(|| gid.to_string(), |group| group.name.clone())
+    }
+}
+
+impl Resolver {
+    fn start() -> Self {
+        let (lookups, lookup_rx) = mpsc::channel();
+        let (entry_tx, entries) = mpsc::channel();
+        thread::Builder::new()
+            .name("nss-lookups".to_owned())
+            .spawn(move || {
+                // Ends once the cache is dropped
+                for lookup in lookup_rx {
+                    let entry = match lookup {
+                        Lookup::User(uid) => Entry::User(uid, UserInfo::lookup(uid)),
+                        Lookup::Group(gid) => Entry::Group(gid, GroupInfo::lookup(gid)),
+                    };
+                    if entry_tx.send(entry).is_err() {
+                        return;
+                    }
+                }
+            })
+            .unwrap();
+        Self { lookups, entries }
+    }
+}
+impl UserInfo {
+    fn lookup(uid: u32) -> Option<Self> {
+        let mut pwd: libc::passwd = unsafe { mem::zeroed() };
+        let _buf = lookup_with_buffer("getpwuid_r", |buf, result| unsafe {
+            libc::getpwuid_r(
+                uid,
+                &mut pwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                result as *mut *mut libc::passwd,
+            )
+        })?;
+        // The strings of `pwd` point into `buf`
+        let user = unsafe {
+            let nologin = to_string(pwd.pw_shell).ends_with("nologin");
+            Self {
+                name: to_string(pwd.pw_name),
+                uid: pwd.pw_uid,
+                gid: pwd.pw_gid,
+                description: to_string(pwd.pw_gecos),
+                kind: if pwd.pw_uid == 0 {
+                    UserKind::Root
+                } else if nologin {
+                    UserKind::Nologin
+                } else {
+                    UserKind::User
+                },
+            }
+        };
+        Some(user)
+    }
+}
+impl GroupInfo {
+    fn lookup(gid: u32) -> Option<Self> {
+        let mut grp: libc::group = unsafe { mem::zeroed() };
+        let _buf = lookup_with_buffer("getgrgid_r", |buf, result| unsafe {
+            libc::getgrgid_r(
+                gid,
+                &mut grp,
+                buf.as_mut_ptr(),
+                buf.len(),
+                result as *mut *mut libc::group,
+            )
+        })?;
+        // The strings of `grp` point into `buf`
+        let group = unsafe {
+            let mut users = Vec::new();
+            let mut member = grp.gr_mem;
+            while !member.is_null() && !(*member).is_null() {
+                users.push(to_string(*member));
+                member = member.add(1);
+            }
+            Self {
+                name: to_string(grp.gr_name),
+                gid: grp.gr_gid,
+                users,
+            }
+        };
+        Some(group)
+    }
+}
+
+/// Calls a reentrant NSS function like `getpwuid_r`, growing its buffer until the entry fits.
+/// Returns the buffer that the entry points into, or `None` if there is no entry.
+fn lookup_with_buffer(
+    name: &str,
+    mut call: impl FnMut(&mut [c_char], *mut *mut libc::c_void) -> libc::c_int,
+) -> Option<Vec<c_char>> {
+    let mut buf = vec![0; 1024];
+    loop {
+        let mut result = ptr::null_mut();
+        match call(&mut buf, &mut result) {
+            0 if result.is_null() => return None,
+            0 => return Some(buf),
+            libc::ERANGE if buf.len() < MAX_ENTRY_LEN => buf.resize(2 * buf.len(), 0),
+            errno => {
+                let err = io::Error::from_raw_os_error(errno);
+                tracing::warn!("{name} failed: {err}");
+                return None;
+            }
+        }
+    }
+}
+
+/// # Safety
+/// `ptr` must be null or point to a nul-terminated string.
+unsafe fn to_string(ptr: *const c_char) -> String {
+    match ptr.is_null() {
+        true => String::new(),
+        false => CStr::from_ptr(ptr).to_string_lossy().into_owned(),
+    }
+}
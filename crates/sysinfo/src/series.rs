@@ -1,28 +1,29 @@
 use util::HISTORY;
 
+/// A ring buffer of the last `N` samples, by default one per tick.
 #[derive(Clone, Debug)]
-pub struct Series<T: Copy + Default> {
-    inner: Box<[T; HISTORY]>,
+pub struct Series<T: Copy + Default, const N: usize = HISTORY> {
+    inner: Box<[T; N]>,
     last: usize,
 }
-impl<T: Copy + Default> Default for Series<T> {
+impl<T: Copy + Default, const N: usize> Default for Series<T, N> {
     fn default() -> Self {
         Self {
-            inner: Box::new([T::default(); HISTORY]),
-            last: HISTORY - 1,
+            inner: Box::new([T::default(); N]),
+            last: N - 1,
         }
     }
 }
-impl<T: Copy + Default> Series<T> {
+impl<T: Copy + Default, const N: usize> Series<T, N> {
     pub fn push(&mut self, item: T) {
         self.last += 1;
-        if self.last == HISTORY {
+        if self.last == N {
             self.last = 0;
         }
         self.inner[self.last] = item;
     }
     pub fn capacity() -> usize {
-        HISTORY
+        N
     }
     pub fn latest(&self) -> T {
         self.inner[self.last]
@@ -3,6 +3,8 @@ use std::{collections::VecDeque, time::Duration};
 pub const SUBSEC: u64 = 60;
 pub const TICK_DELAY: Duration = Duration::from_micros(1_000_000 / SUBSEC);
 pub const HISTORY: usize = (60 * SUBSEC + 1) as usize;
+/// Processes are sampled once per second, over the same time span as `HISTORY`.
+pub const PROC_HISTORY: usize = (HISTORY - 1) / SUBSEC as usize + 1;
 
 #[derive(Clone, Debug)]
 pub struct WindowMovingAverage<const WINDOW_SIZE: usize> {